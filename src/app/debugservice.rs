@@ -35,22 +35,26 @@ impl DebugService {
     }
 
     pub async fn send_debug_event(&self, msg: &str) {
-        if let Some(debug_session) = self.debug_session.try_lock() {
-            match debug_session.as_ref() {
-                Some(session) => {
-                    log::debug!("••• {}", msg);
-                    let now: DateTime<Local> = std::time::SystemTime::now().into();
-                    let msg_format = format!(
-                        r#"<div id="debug" hx-swap-oob="afterbegin"><p><b>• {} : </b>{}</p></div>"#,
-                        now.format(DATE_FORMAT),
-                        msg
-                    );
-                    if let Some(err) = session.clone().text(msg_format).await.err() {
-                        log::error!("failed to send debug event: {msg} error: {err}");
-                    }
+        log::debug!("••• {}", msg);
+        let now: DateTime<Local> = std::time::SystemTime::now().into();
+        let msg_format = format!(
+            r#"<div id="debug" hx-swap-oob="afterbegin"><p><b>• {} : </b>{}</p></div>"#,
+            now.format(DATE_FORMAT),
+            msg
+        );
+        self.send_fragment(&msg_format).await;
+    }
+
+    /// Sends html with `hx-swap-oob` elements, which htmx swaps into the page by their ids.
+    pub async fn send_fragment(&self, html: &str) {
+        let debug_session = self.debug_session.lock().await;
+        match debug_session.as_ref() {
+            Some(session) => {
+                if let Some(err) = session.clone().text(html.to_owned()).await.err() {
+                    log::error!("failed to send to debug session: {html} error: {err}");
                 }
-                None => log::warn!("failed to send debug event (no session): {msg}"),
             }
+            None => log::debug!("no debug session to send the fragment to"),
         }
     }
 }
@@ -0,0 +1,174 @@
+use crate::{
+    app::model::{ContractInstances, DEFAULT_INSTANCE_LABEL},
+    client::EthereumClient,
+    AppError,
+};
+use ethers::types::{Address, TransactionReceipt, H256};
+use ethers_providers::Middleware;
+use futures::lock::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs, path::PathBuf, sync::Arc};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Deployment {
+    pub chain_id: u64,
+    pub contract_name: String,
+    #[serde(default = "default_label")]
+    pub label: String,
+    pub bytecode_hash: H256,
+    pub address: Address,
+    pub deployer: Address,
+    pub tx_hash: H256,
+    pub block_number: u64,
+    pub constructor_args: Vec<String>,
+}
+
+impl Deployment {
+    pub fn new(
+        eth_client: &EthereumClient,
+        contract_name: &str,
+        label: &str,
+        bytecode_hash: H256,
+        receipt: &TransactionReceipt,
+        constructor_args: Vec<String>,
+    ) -> Self {
+        Deployment {
+            chain_id: eth_client.chain_id(),
+            contract_name: contract_name.to_owned(),
+            label: label.to_owned(),
+            bytecode_hash,
+            address: receipt.contract_address.unwrap_or_default(),
+            deployer: receipt.from,
+            tx_hash: receipt.transaction_hash,
+            block_number: receipt.block_number.unwrap_or_default().as_u64(),
+            constructor_args,
+        }
+    }
+
+    fn same_key(&self, other: &Deployment) -> bool {
+        self.chain_id == other.chain_id
+            && self.contract_name == other.contract_name
+            && self.label == other.label
+            && self.bytecode_hash == other.bytecode_hash
+    }
+}
+
+fn default_label() -> String {
+    DEFAULT_INSTANCE_LABEL.to_owned()
+}
+
+#[derive(Clone)]
+pub struct DeploymentRegistry {
+    path: PathBuf,
+    deployments: Arc<Mutex<Vec<Deployment>>>,
+}
+
+impl DeploymentRegistry {
+    pub fn load(path: &str) -> Result<Self, AppError> {
+        let path = PathBuf::from(path);
+        let deployments = if path.exists() {
+            let content = fs::read_to_string(&path).map_err(|e| {
+                AppError::DeploymentRegistryError(path.display().to_string(), e.into())
+            })?;
+            serde_json::from_str(&content).map_err(|e| {
+                AppError::DeploymentRegistryError(path.display().to_string(), e.into())
+            })?
+        } else {
+            vec![]
+        };
+
+        Ok(Self {
+            path,
+            deployments: Arc::new(Mutex::new(deployments)),
+        })
+    }
+
+    pub async fn record(&self, deployment: Deployment) -> Result<(), AppError> {
+        let mut deployments = self.deployments.lock().await;
+        deployments.retain(|d| !d.same_key(&deployment));
+        deployments.push(deployment);
+        self.save(&deployments)
+    }
+
+    /// Removes the deployments of the current chain which have no code at their address anymore
+    /// (e.g. after restarting the local node). Deployments of other chains are kept.
+    pub async fn prune(&self, eth_client: &EthereumClient) -> Result<Vec<Deployment>, AppError> {
+        let eth = eth_client.get_client();
+        let chain_id = node_chain_id(eth_client).await?;
+
+        let mut deployments = self.deployments.lock().await;
+        let mut kept = vec![];
+        let mut pruned = vec![];
+        for deployment in deployments.drain(..) {
+            let keep = deployment.chain_id != chain_id
+                || match eth.get_code(deployment.address, None).await {
+                    Ok(code) => !code.is_empty(),
+                    Err(e) => return Err(AppError::NodeRequestError(e.into())),
+                };
+            if keep {
+                kept.push(deployment);
+            } else {
+                pruned.push(deployment);
+            }
+        }
+        *deployments = kept;
+
+        if !pruned.is_empty() {
+            self.save(&deployments)?;
+        }
+        Ok(pruned)
+    }
+
+    /// Recreates the contract instances of the current chain which match the compiled bytecode,
+    /// skipping the deployments of other chains.
+    pub async fn reattach(
+        &self,
+        eth_client: &EthereumClient,
+    ) -> Result<HashMap<String, ContractInstances>, AppError> {
+        let chain_id = node_chain_id(eth_client).await?;
+        let deployments = self.deployments.lock().await;
+        let mut contracts: HashMap<String, ContractInstances> = HashMap::new();
+        for deployment in deployments.iter().filter(|d| d.chain_id == chain_id) {
+            match eth_client.bytecode_hash(&deployment.contract_name) {
+                Ok(hash) if hash == deployment.bytecode_hash => {}
+                _ => continue,
+            }
+
+            let address = format!("{:#x}", deployment.address);
+            match eth_client
+                .contract_from_address(&deployment.contract_name, &address)
+                .await
+            {
+                Ok(contract) => contracts
+                    .entry(deployment.contract_name.clone())
+                    .or_default()
+                    .insert(&deployment.label, contract),
+                Err(e) => log::warn!(
+                    "could not reattach {} ({}) at {address}: {e}",
+                    deployment.contract_name,
+                    deployment.label
+                ),
+            }
+        }
+
+        Ok(contracts)
+    }
+
+    fn save(&self, deployments: &[Deployment]) -> Result<(), AppError> {
+        let content = serde_json::to_string_pretty(deployments).map_err(|e| {
+            AppError::DeploymentRegistryError(self.path.display().to_string(), e.into())
+        })?;
+        fs::write(&self.path, content).map_err(|e| {
+            AppError::DeploymentRegistryError(self.path.display().to_string(), e.into())
+        })
+    }
+}
+
+/// Chain id reported by the node, which decides for both pruning and reattaching which
+/// deployments belong to the current chain.
+async fn node_chain_id(eth_client: &EthereumClient) -> Result<u64, AppError> {
+    match eth_client.get_client().get_chainid().await {
+        Ok(chain_id) => Ok(chain_id.as_u64()),
+        Err(e) => Err(AppError::NodeRequestError(e.into())),
+    }
+}
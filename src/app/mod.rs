@@ -1,2 +1,4 @@
 pub mod debugservice;
+pub mod deploymentregistry;
 pub mod model;
+pub mod txtracker;
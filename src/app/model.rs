@@ -1,19 +1,94 @@
-use std::{collections::HashMap, env, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    env,
+    sync::Arc,
+};
 
-use crate::app::debugservice::DebugService;
+use crate::app::{
+    debugservice::DebugService, deploymentregistry::DeploymentRegistry, txtracker::TxTracker,
+};
 use crate::client::{ContractInstanceType, EthereumClient};
-use ethers::types::Address;
+use ethers::types::{Address, U256};
 use futures::lock::Mutex;
+use serde::Serialize;
 use tera::Tera;
 
 pub struct State {
     pub tmpl: Tera,
     pub eth_client: EthereumClient,
-    pub contracts: Arc<Mutex<HashMap<String, ContractInstanceType>>>,
+    pub contracts: Arc<Mutex<HashMap<String, ContractInstances>>>,
     pub debug_service: DebugService,
+    pub deployments: DeploymentRegistry,
+    pub snapshots: Arc<Mutex<Vec<Snapshot>>>,
+    pub tx_tracker: TxTracker,
     pub accounts: Vec<Address>,
 }
 
+/// Named chain state snapshot taken with `evm_snapshot`.
+#[derive(Serialize, Clone)]
+pub struct Snapshot {
+    pub id: U256,
+    pub name: String,
+    pub block_number: u64,
+    pub created_at: String,
+}
+
+pub const DEFAULT_INSTANCE_LABEL: &str = "default";
+
+/// Labelled instances of the same contract with the one currently used by the lab.
+#[derive(Default)]
+pub struct ContractInstances {
+    active: Option<String>,
+    instances: BTreeMap<String, ContractInstanceType>,
+}
+
+impl ContractInstances {
+    pub fn active(&self) -> Option<&ContractInstanceType> {
+        self.active
+            .as_ref()
+            .and_then(|label| self.instances.get(label))
+    }
+
+    pub fn active_label(&self) -> Option<&str> {
+        self.active.as_deref()
+    }
+
+    pub fn get(&self, label: &str) -> Option<&ContractInstanceType> {
+        self.instances.get(label)
+    }
+
+    pub fn insert(&mut self, label: &str, contract: ContractInstanceType) {
+        self.instances.insert(label.to_owned(), contract);
+        self.active = Some(label.to_owned());
+    }
+
+    pub fn set_active(&mut self, label: &str) -> bool {
+        if self.instances.contains_key(label) {
+            self.active = Some(label.to_owned());
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Removes the instance and activates the first remaining one if it was the active one.
+    pub fn remove(&mut self, label: &str) -> Option<ContractInstanceType> {
+        let removed = self.instances.remove(label);
+        if self.active.as_deref() == Some(label) {
+            self.active = self.instances.keys().next().cloned();
+        }
+        removed
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &ContractInstanceType)> {
+        self.instances.iter()
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("env var {} must be set", .0)]
@@ -24,4 +99,13 @@ pub enum Error {
 
     #[error("no block found at hash or number{}", .0)]
     NoBlockFoundError(String),
+
+    #[error("could not access deployment registry {}", .0)]
+    DeploymentRegistryError(String, #[source] Box<dyn std::error::Error>),
+
+    #[error("node request failed")]
+    NodeRequestError(#[source] Box<dyn std::error::Error>),
+
+    #[error("could not start anvil node: {}", .0)]
+    AnvilSpawnError(String),
 }
@@ -0,0 +1,491 @@
+use crate::{
+    app::debugservice::DebugService,
+    client::{
+        fees::{self, FeeOverrides},
+        EthereumClient,
+    },
+    helper,
+};
+use chrono::Local;
+use ethers::types::{
+    transaction::eip2718::TypedTransaction, Address, BlockNumber, Eip1559TransactionRequest,
+    Eip2930TransactionRequest, TransactionRequest, H256, U256,
+};
+use ethers_providers::Middleware;
+use futures::lock::Mutex;
+use serde::Serialize;
+use std::{sync::Arc, time::Duration};
+use tera::{Context, Tera};
+
+pub const TRANSACTIONS_TEMPLATE: &str = "transactions.html";
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+const MAX_TRACKED: usize = 20;
+const DATE_FORMAT: &str = "%T";
+// nodes only accept a replacement raising the fees by at least 10%
+const FEE_BUMP_PERCENT: u64 = 20;
+const TRANSFER_GAS: u64 = 21_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum TxStatus {
+    Pending,
+    Mined {
+        block_number: u64,
+        block_hash: H256,
+        confirmations: u64,
+        success: bool,
+        gas_used: String,
+        effective_gas_price: String,
+        revert: Option<String>,
+    },
+    Dropped,
+    Replaced {
+        by: Option<H256>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackedTx {
+    pub hash: H256,
+    pub label: String,
+    pub from: Address,
+    pub nonce: Option<u64>,
+    pub fees: String,
+    pub submitted_at: String,
+    pub status: TxStatus,
+    // blocks on top of and including the one of the receipt for it to count as final
+    pub confirmations: u64,
+    // how often its receipt was reorged out of the chain
+    pub reorgs: u32,
+    // the sent transaction, needed to replace it
+    #[serde(skip)]
+    tx: Option<TypedTransaction>,
+    #[serde(skip)]
+    replaced_by: Option<H256>,
+    #[serde(skip)]
+    watching: bool,
+}
+
+/// Registry of the submitted transactions, which are watched in the background until they are
+/// confirmed, dropped or replaced. Every change is pushed to the browser over the debug websocket.
+/// Confirmed transactions are not watched anymore, so a deeper reorg is only noticed through
+/// [`TxTracker::recheck`], which the reorg and snapshot revert routes call.
+#[derive(Clone)]
+pub struct TxTracker {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    eth_client: EthereumClient,
+    debug_service: DebugService,
+    tmpl: Tera,
+    transactions: Mutex<Vec<TrackedTx>>,
+}
+
+impl TxTracker {
+    pub fn new(eth_client: EthereumClient, debug_service: DebugService, tmpl: Tera) -> Self {
+        TxTracker {
+            inner: Arc::new(Inner {
+                eth_client,
+                debug_service,
+                tmpl,
+                transactions: Mutex::new(vec![]),
+            }),
+        }
+    }
+
+    /// Most recent transactions first.
+    pub async fn transactions(&self) -> Vec<TrackedTx> {
+        self.inner.transactions.lock().await.clone()
+    }
+
+    /// Starts tracking a sent transaction until it has the given number of confirmations.
+    pub async fn track(&self, hash: H256, label: &str, fees: &str, confirmations: u64) {
+        let client = self.inner.eth_client.get_client();
+        let tx = match client.get_transaction(hash).await {
+            Ok(tx) => tx,
+            Err(e) => {
+                log::warn!("could not load transaction {hash:#x}: {e}");
+                None
+            }
+        };
+        let tracked = TrackedTx {
+            hash,
+            label: label.to_owned(),
+            from: tx.as_ref().map(|tx| tx.from).unwrap_or_default(),
+            nonce: tx.as_ref().map(|tx| tx.nonce.as_u64()),
+            fees: fees.to_owned(),
+            submitted_at: Local::now().format(DATE_FORMAT).to_string(),
+            status: TxStatus::Pending,
+            confirmations,
+            reorgs: 0,
+            tx: tx.as_ref().map(Into::into),
+            replaced_by: None,
+            watching: true,
+        };
+
+        let mut transactions = self.inner.transactions.lock().await;
+        transactions.insert(0, tracked);
+        // the watcher of an evicted transaction stops once it no longer finds it
+        transactions.truncate(MAX_TRACKED);
+        drop(transactions);
+        self.push(false).await;
+
+        actix_web::rt::spawn(self.clone().watch(hash));
+    }
+
+    /// Resends the pending transaction with the same nonce and higher fees.
+    pub async fn speed_up(&self, hash: H256) -> Result<H256, String> {
+        self.replace(hash, false).await
+    }
+
+    /// Replaces the pending transaction with a zero value transfer to the sender itself.
+    pub async fn cancel(&self, hash: H256) -> Result<H256, String> {
+        self.replace(hash, true).await
+    }
+
+    async fn replace(&self, hash: H256, cancel: bool) -> Result<H256, String> {
+        let tracked = self
+            .inner
+            .transactions
+            .lock()
+            .await
+            .iter()
+            .find(|tx| tx.hash == hash)
+            .cloned()
+            .ok_or(format!("transaction {hash:#x} is not tracked"))?;
+        if !matches!(tracked.status, TxStatus::Pending) {
+            return Err(format!("transaction {hash:#x} is no longer pending"));
+        }
+        let tx = tracked
+            .tx
+            .ok_or(format!("transaction {hash:#x} could not be loaded"))?;
+        let nonce = *tx.nonce().ok_or("transaction has no nonce")?;
+
+        let mut replacement = match (cancel, &tx) {
+            (false, _) => tx.clone(),
+            (true, TypedTransaction::Legacy(_)) => TransactionRequest::new().into(),
+            (true, TypedTransaction::Eip2930(eip2930)) => Eip2930TransactionRequest::new(
+                TransactionRequest::new(),
+                eip2930.access_list.clone(),
+            )
+            .into(),
+            (true, TypedTransaction::Eip1559(_)) => Eip1559TransactionRequest::new().into(),
+        };
+        if cancel {
+            replacement
+                .set_to(tracked.from)
+                .set_value(U256::zero())
+                .set_gas(TRANSFER_GAS);
+        }
+        replacement.set_from(tracked.from).set_nonce(nonce);
+
+        let overrides = match &tx {
+            TypedTransaction::Eip1559(eip1559) => FeeOverrides {
+                max_fee_per_gas: Some(bump(eip1559.max_fee_per_gas.unwrap_or_default())),
+                max_priority_fee_per_gas: Some(bump(
+                    eip1559.max_priority_fee_per_gas.unwrap_or_default(),
+                )),
+                ..Default::default()
+            },
+            // keeps its type and access list, `fees::apply` only sets the gas price
+            TypedTransaction::Eip2930(eip2930) => FeeOverrides {
+                gas_price: Some(bump(eip2930.tx.gas_price.unwrap_or_default())),
+                ..Default::default()
+            },
+            TypedTransaction::Legacy(legacy) => FeeOverrides {
+                gas_price: Some(bump(legacy.gas_price.unwrap_or_default())),
+                ..Default::default()
+            },
+        };
+        let eth_client = &self.inner.eth_client;
+        let applied = eth_client
+            .apply_fees(&mut replacement, &overrides)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        // transactions of accounts without a signer were sent impersonated
+        let signer = eth_client.get_signer_client(Some(tracked.from));
+        let impersonated = signer.is_none();
+        let client = signer.unwrap_or(eth_client.get_client());
+        let send = async {
+            client
+                .send_transaction(replacement, None)
+                .await
+                .map(|pending_tx| pending_tx.tx_hash())
+                .map_err(|e| e.to_string())
+        };
+        let result = match impersonated {
+            true => eth_client
+                .impersonated(tracked.from, send)
+                .await
+                .map_err(|e| e.to_string())?,
+            false => send.await,
+        };
+        let new_hash = result?;
+
+        if let Some(tracked) = self
+            .inner
+            .transactions
+            .lock()
+            .await
+            .iter_mut()
+            .find(|tx| tx.hash == hash)
+        {
+            tracked.replaced_by = Some(new_hash);
+        }
+        let label = match cancel {
+            true => format!("cancel {}", tracked.label),
+            false => format!("{} (sped up)", tracked.label),
+        };
+        self.inner
+            .debug_service
+            .send_debug_event(&format!(
+                "<b>[{label}]</b> replacing {} with {}, {applied}",
+                helper::tx_link(&hash),
+                helper::tx_link(&new_hash)
+            ))
+            .await;
+        self.track(
+            new_hash,
+            &label,
+            &applied.to_string(),
+            tracked.confirmations,
+        )
+        .await;
+        Ok(new_hash)
+    }
+
+    /// Watches the mined transactions again, including the confirmed ones, e.g. after the chain
+    /// was reverted to a snapshot, to notice receipts which are no longer canonical.
+    pub async fn recheck(&self) {
+        let mut hashes = vec![];
+        for tx in self.inner.transactions.lock().await.iter_mut() {
+            if !tx.watching && matches!(tx.status, TxStatus::Mined { .. }) {
+                tx.watching = true;
+                hashes.push(tx.hash);
+            }
+        }
+        for hash in hashes {
+            actix_web::rt::spawn(self.clone().watch(hash));
+        }
+    }
+
+    /// Polls the transaction until it is confirmed, dropped or replaced.
+    async fn watch(self, hash: H256) {
+        loop {
+            actix_web::rt::time::sleep(POLL_INTERVAL).await;
+            let tracked = match self
+                .inner
+                .transactions
+                .lock()
+                .await
+                .iter()
+                .find(|tx| tx.hash == hash)
+            {
+                Some(tracked) => tracked.clone(),
+                None => return,
+            };
+            match self.check(&tracked).await {
+                Ok(status) => {
+                    if self.update(tracked, status).await {
+                        return;
+                    }
+                }
+                Err(e) => log::debug!("could not check transaction {hash:#x}: {e}"),
+            }
+        }
+    }
+
+    async fn check(&self, tracked: &TrackedTx) -> Result<TxStatus, String> {
+        let eth_client = &self.inner.eth_client;
+        let client = eth_client.get_client();
+        if let Some(receipt) = client
+            .get_transaction_receipt(tracked.hash)
+            .await
+            .map_err(|e| e.to_string())?
+        {
+            let block_number = receipt.block_number.unwrap_or_default();
+            let block_hash = receipt.block_hash.unwrap_or_default();
+            // a receipt can outlive its block for a moment after a reorg
+            let canonical = client
+                .get_block(block_number)
+                .await
+                .map_err(|e| e.to_string())?
+                .and_then(|block| block.hash);
+            if canonical == Some(block_hash) {
+                let head = client.get_block_number().await.map_err(|e| e.to_string())?;
+                let success = receipt.status == Some(1.into());
+                let revert = match &tracked.status {
+                    TxStatus::Mined {
+                        block_hash: mined_in,
+                        revert,
+                        ..
+                    } if *mined_in == block_hash => revert.clone(),
+                    _ if success => None,
+                    _ => Some(
+                        eth_client
+                            .transaction_revert(tracked.hash)
+                            .await
+                            .to_string(),
+                    ),
+                };
+                return Ok(TxStatus::Mined {
+                    block_number: block_number.as_u64(),
+                    block_hash,
+                    confirmations: (head.saturating_sub(block_number) + 1).as_u64(),
+                    success,
+                    gas_used: receipt.gas_used.unwrap_or_default().to_string(),
+                    effective_gas_price: fees::gwei(
+                        receipt.effective_gas_price.unwrap_or_default(),
+                    ),
+                    revert,
+                });
+            }
+        }
+        if client
+            .get_transaction(tracked.hash)
+            .await
+            .map_err(|e| e.to_string())?
+            .is_some()
+        {
+            return Ok(TxStatus::Pending);
+        }
+
+        // neither mined nor in the pool anymore
+        if tracked.replaced_by.is_some() {
+            return Ok(TxStatus::Replaced {
+                by: tracked.replaced_by,
+            });
+        }
+        let next_nonce = client
+            .get_transaction_count(tracked.from, Some(BlockNumber::Latest.into()))
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(match tracked.nonce {
+            Some(nonce) if U256::from(nonce) < next_nonce => TxStatus::Replaced { by: None },
+            _ => TxStatus::Dropped,
+        })
+    }
+
+    /// Stores the new status, reporting what changed. Returns whether the transaction is final.
+    async fn update(&self, tracked: TrackedTx, status: TxStatus) -> bool {
+        let link = helper::tx_link(&tracked.hash);
+        let reorged = match (&tracked.status, &status) {
+            (
+                TxStatus::Mined { block_hash, .. },
+                TxStatus::Mined {
+                    block_hash: new, ..
+                },
+            ) => block_hash != new,
+            (TxStatus::Mined { .. }, _) => true,
+            _ => false,
+        };
+        let mut events = vec![];
+        if let TxStatus::Mined {
+            block_number,
+            block_hash,
+            ..
+        } = &tracked.status
+        {
+            if reorged {
+                events.push(format!(
+                    "receipt of transaction {link} in block {block_number} ({block_hash:#x}) \
+                     is no longer canonical"
+                ));
+            }
+        }
+        let mined = matches!(tracked.status, TxStatus::Mined { .. });
+        let newly_mined = matches!(status, TxStatus::Mined { .. }) && (reorged || !mined);
+        let was_confirmed = !reorged
+            && matches!(tracked.status, TxStatus::Mined { confirmations, .. }
+                if confirmations >= tracked.confirmations);
+        let final_status = match &status {
+            TxStatus::Pending => false,
+            TxStatus::Mined { confirmations, .. } => *confirmations >= tracked.confirmations,
+            TxStatus::Dropped | TxStatus::Replaced { .. } => true,
+        };
+        match &status {
+            TxStatus::Mined {
+                block_number,
+                success: true,
+                gas_used,
+                effective_gas_price,
+                ..
+            } if newly_mined => events.push(format!(
+                "transaction {link} mined in block {block_number}, {}, gas used {gas_used}, \
+                 effective gas price {effective_gas_price} gwei",
+                tracked.fees
+            )),
+            TxStatus::Mined {
+                block_number,
+                revert,
+                ..
+            } if newly_mined => events.push(format!(
+                "transaction {link} reverted in block {block_number}: {}",
+                revert.as_deref().unwrap_or_default()
+            )),
+            TxStatus::Dropped => events.push(format!("transaction {link} was dropped")),
+            TxStatus::Replaced { by } => events.push(format!(
+                "transaction {link} was replaced{}",
+                by.map(|by| format!(" by {}", helper::tx_link(&by)))
+                    .unwrap_or_default()
+            )),
+            _ => {}
+        }
+        if final_status && !was_confirmed && tracked.confirmations > 1 {
+            if let TxStatus::Mined { confirmations, .. } = &status {
+                events.push(format!(
+                    "transaction {link} confirmed by {confirmations} blocks"
+                ));
+            }
+        }
+        for event in events.iter() {
+            self.inner
+                .debug_service
+                .send_debug_event(&format!("<b>[{}]</b> {event}", tracked.label))
+                .await;
+        }
+
+        let changed = match self
+            .inner
+            .transactions
+            .lock()
+            .await
+            .iter_mut()
+            .find(|tx| tx.hash == tracked.hash)
+        {
+            Some(tx) => {
+                let changed = tx.status != status;
+                if reorged {
+                    tx.reorgs += 1;
+                }
+                tx.status = status;
+                tx.watching = !final_status;
+                changed
+            }
+            None => false,
+        };
+        if changed {
+            self.push(newly_mined || reorged).await;
+        }
+        final_status
+    }
+
+    /// Pushes the transaction list to the browser, asking the lab panels to reload if the chain
+    /// state changed.
+    async fn push(&self, reload: bool) {
+        let mut context = Context::new();
+        context.insert("transactions", &self.transactions().await);
+        context.insert("oob", &true);
+        context.insert("reload", &reload);
+        match self.inner.tmpl.render(TRANSACTIONS_TEMPLATE, &context) {
+            Ok(rendered) => self.inner.debug_service.send_fragment(&rendered).await,
+            Err(e) => log::error!("could not render transactions: {e}"),
+        }
+    }
+}
+
+fn bump(fee: U256) -> U256 {
+    fee + fee * FEE_BUMP_PERCENT / 100 + 1
+}
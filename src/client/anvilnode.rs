@@ -0,0 +1,216 @@
+use crate::{
+    client::ethereumclient::{
+        ACCOUNT, CHAIN_ID, ENDPOINT, KEYSTORE, MNEMONIC, OTHER_PRIVATE_KEYS, PRIVATE_KEY,
+    },
+    helper::get_env_var,
+    AppError,
+};
+
+use std::{
+    env,
+    io::{BufRead, BufReader},
+    net::TcpListener,
+    process::{Child, Command, Stdio},
+    str::FromStr,
+    thread,
+};
+
+const ANVIL: &str = "ANVIL";
+const ANVIL_PORT: &str = "ANVIL_PORT";
+const ANVIL_CHAIN_ID: &str = "ANVIL_CHAIN_ID";
+const ANVIL_BLOCK_TIME: &str = "ANVIL_BLOCK_TIME";
+const ANVIL_ACCOUNTS: &str = "ANVIL_ACCOUNTS";
+const ANVIL_PROGRAM: &str = "anvil";
+const ANVIL_DEFAULT_CHAIN_ID: u64 = 31337;
+
+/// Env vars of an externally configured node which are replaced by the spawned instance.
+const OVERRIDDEN_ENV_VARS: [&str; 3] = [KEYSTORE, MNEMONIC, ACCOUNT];
+
+/// Anvil node spawned by the app, killed when dropped.
+pub struct AnvilNode {
+    child: Child,
+    endpoint: String,
+    chain_id: u64,
+    keys: Vec<String>,
+}
+
+impl AnvilNode {
+    /// Starts anvil and waits until it listens. Unlike `ethers::utils::Anvil::spawn` it does not
+    /// panic if anvil cannot be started but returns an error with the output of the node.
+    fn spawn(
+        port: Option<u16>,
+        chain_id: Option<u64>,
+        args: Vec<String>,
+    ) -> Result<Self, AppError> {
+        let port = match port {
+            Some(port) => port,
+            None => unused_port()?,
+        };
+        let mut command = Command::new(ANVIL_PROGRAM);
+        command.arg("--port").arg(port.to_string());
+        if let Some(chain_id) = chain_id {
+            command.arg("--chain-id").arg(chain_id.to_string());
+        }
+        let mut child = command
+            .args(args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                AppError::AnvilSpawnError(format!(
+                    "could not run {ANVIL_PROGRAM}, is foundry installed? {e}"
+                ))
+            })?;
+
+        // stderr is read on its own thread so that anvil never blocks on a full pipe
+        let stderr = child.stderr.take().map(|stderr| {
+            thread::spawn(move || {
+                let mut output = vec![];
+                for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                    log::warn!("{ANVIL_PROGRAM}: {line}");
+                    output.push(line);
+                }
+                output.join("\n")
+            })
+        });
+        let mut stdout = match child.stdout.take() {
+            Some(stdout) => BufReader::new(stdout),
+            None => return Err(AppError::AnvilSpawnError("no output of anvil".to_owned())),
+        };
+
+        let mut printed_chain_id = None;
+        let mut keys = vec![];
+        let mut private_keys = false;
+        let mut chain_id_heading = false;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            match stdout.read_line(&mut line) {
+                Ok(0) | Err(_) => {
+                    // anvil exited, e.g. because the port is in use
+                    let _ = child.kill();
+                    let status = child.wait().map(|s| s.to_string()).unwrap_or_default();
+                    let output = stderr
+                        .and_then(|stderr| stderr.join().ok())
+                        .unwrap_or_default();
+                    return Err(AppError::AnvilSpawnError(format!(
+                        "{ANVIL_PROGRAM} exited ({status}): {output}"
+                    )));
+                }
+                Ok(_) => {}
+            }
+            if line.contains("Listening on") {
+                break;
+            }
+            if line.starts_with("Private Keys") {
+                private_keys = true;
+            } else if private_keys && line.starts_with('(') {
+                if let Some(key) = line.split("0x").last() {
+                    keys.push(format!("0x{}", key.trim()));
+                }
+            }
+            // printed either as `Chain ID: <id>` or as a heading followed by the id
+            if let Some(rest) = line.trim().strip_prefix("Chain ID") {
+                chain_id_heading = true;
+                private_keys = false;
+                printed_chain_id = rest.trim_start_matches(':').trim().parse::<u64>().ok();
+            } else if chain_id_heading && printed_chain_id.is_none() {
+                printed_chain_id = line.trim().parse::<u64>().ok();
+            }
+        }
+        // the configured chain id takes precedence, the printed one is only a fallback
+        let chain_id = chain_id
+            .or(printed_chain_id)
+            .unwrap_or(ANVIL_DEFAULT_CHAIN_ID);
+
+        // anvil logs every request, the output is drained so that it never blocks
+        thread::spawn(move || {
+            for line in stdout.lines().map_while(Result::ok) {
+                log::trace!("{ANVIL_PROGRAM}: {line}");
+            }
+        });
+
+        Ok(AnvilNode {
+            child,
+            endpoint: format!("ws://localhost:{port}"),
+            chain_id,
+            keys,
+        })
+    }
+}
+
+impl Drop for AnvilNode {
+    fn drop(&mut self) {
+        if let Err(e) = self.child.kill() {
+            log::error!("could not stop {ANVIL_PROGRAM}: {e}");
+        }
+    }
+}
+
+/// Spawns a local anvil node if `ANVIL=true` and points `ENDPOINT`, `CHAIN_ID`, `PRIVATE_KEY`
+/// and `OTHER_PRIVATE_KEYS` to it. The node is killed when the returned instance is dropped.
+pub fn spawn_from_env() -> Result<Option<AnvilNode>, AppError> {
+    if !get_env_var(ANVIL).is_ok_and(|v| v == "true") {
+        return Ok(None);
+    }
+
+    let port = parse_env_var::<u16>(ANVIL_PORT)?;
+    let chain_id = parse_env_var::<u64>(ANVIL_CHAIN_ID)?;
+    let mut args = vec![];
+    if let Some(block_time) = parse_env_var::<u64>(ANVIL_BLOCK_TIME)? {
+        args.extend(["--block-time".to_owned(), block_time.to_string()]);
+    }
+    if let Some(accounts) = parse_env_var::<u32>(ANVIL_ACCOUNTS)? {
+        args.extend(["--accounts".to_owned(), accounts.to_string()]);
+    }
+    let node = AnvilNode::spawn(port, chain_id, args)?;
+
+    let (private_key, other_keys) = match node.keys.split_first() {
+        Some(keys) => keys,
+        None => {
+            return Err(AppError::AnvilSpawnError(
+                "spawned anvil node has no accounts".to_owned(),
+            ))
+        }
+    };
+
+    for key in OVERRIDDEN_ENV_VARS {
+        if env::var(key).is_ok() {
+            log::warn!("{key} is ignored while running the spawned anvil node");
+            env::remove_var(key);
+        }
+    }
+    env::set_var(ENDPOINT, &node.endpoint);
+    env::set_var(CHAIN_ID, node.chain_id.to_string());
+    env::set_var(PRIVATE_KEY, private_key);
+    env::set_var(OTHER_PRIVATE_KEYS, other_keys.join(" "));
+
+    log::info!(
+        "spawned anvil node at {} (chain id {}) with {} accounts",
+        node.endpoint,
+        node.chain_id,
+        node.keys.len()
+    );
+    Ok(Some(node))
+}
+
+fn unused_port() -> Result<u16, AppError> {
+    TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .map(|addr| addr.port())
+        .map_err(|e| AppError::AnvilSpawnError(format!("could not find a free port: {e}")))
+}
+
+fn parse_env_var<T: FromStr>(key: &str) -> Result<Option<T>, AppError>
+where
+    T::Err: std::fmt::Display,
+{
+    match get_env_var(key) {
+        Ok(value) => value
+            .trim()
+            .parse::<T>()
+            .map(Some)
+            .map_err(|e| AppError::AnvilSpawnError(format!("{key} could not be parsed: {e}"))),
+        Err(_) => Ok(None),
+    }
+}
@@ -1,23 +1,51 @@
-use crate::helper::{self, get_env_var};
+use crate::{
+    client::{
+        fees::{self, AppliedFees, FeeOverrides, FeeStrategy},
+        keystore,
+        noncemanager::NonceManager,
+        revert::{self, Revert},
+        transport::Transport,
+    },
+    helper::{self, get_env_var},
+};
 
 use ethers::{
-    abi::Tokenize,
+    abi::{Abi, Tokenize},
     contract::ContractFactory,
     contract::ContractInstance,
     middleware::SignerMiddleware,
     prelude::Wallet,
-    providers::{Provider, Ws},
-    signers::{LocalWallet, Signer},
+    providers::{Middleware, Provider},
+    signers::{coins_bip39::English, LocalWallet, MnemonicBuilder, Signer},
+    types::{
+        transaction::eip2718::TypedTransaction, Address, BlockNumber, Bytes, CallFrame,
+        DefaultFrame, TransactionReceipt, H256, U256,
+    },
+    utils::keccak256,
 };
 use ethers_contract::Contract;
 use ethers_solc::{remappings::Remapping, CompilerInput, CompilerOutput, Solc};
+use futures::lock::Mutex;
 use k256::Secp256k1;
-use std::{path::Path, sync::Arc};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{collections::HashMap, fmt::Debug, future::Future, path::Path, sync::Arc};
 
-const PRIVATE_KEY: &str = "PRIVATE_KEY";
-const ENDPOINT: &str = "ENDPOINT";
-const CHAIN_ID: &str = "CHAIN_ID";
+pub const PRIVATE_KEY: &str = "PRIVATE_KEY";
+pub const KEYSTORE: &str = "KEYSTORE";
+pub const ENDPOINT: &str = "ENDPOINT";
+pub const CHAIN_ID: &str = "CHAIN_ID";
 const CONTRACTS_PATH: &str = "CONTRACTS_PATH";
+pub const OTHER_PRIVATE_KEYS: &str = "OTHER_PRIVATE_KEYS";
+pub const ACCOUNT: &str = "ACCOUNT";
+pub const MNEMONIC: &str = "MNEMONIC";
+const MNEMONIC_DERIVATION_PATH: &str = "MNEMONIC_DERIVATION_PATH";
+const MNEMONIC_ACCOUNT_COUNT: &str = "MNEMONIC_ACCOUNT_COUNT";
+const DEFAULT_DERIVATION_PATH: &str = "m/44'/60'/0'/0";
+const DEFAULT_MNEMONIC_ACCOUNT_COUNT: u32 = 10;
+const IMPERSONATION: &str = "IMPERSONATION";
+const CONFIRMATIONS: &str = "CONFIRMATIONS";
+const DEFAULT_CONFIRMATIONS: u64 = 1;
+const RELAYER: &str = "RELAYER";
 
 #[derive(Debug, thiserror::Error)]
 pub enum EthereumClientError {
@@ -47,19 +75,48 @@ pub enum EthereumClientError {
 
     #[error("could not deploy contract: {}", .0)]
     ContractDeploymentError(#[source] Box<dyn std::error::Error>),
+
+    #[error("{}", .0)]
+    KeystoreError(String, #[source] Box<dyn std::error::Error>),
+
+    #[error("configured account {:#x} does not match the signer {:#x}", .0, .1)]
+    AccountMismatch(Address, Address),
+
+    #[error("signer {:#x} of {} is not one of the {} accounts derived from MNEMONIC", .0, .1, .2)]
+    MnemonicMismatch(Address, String, usize),
+
+    #[error("node request {} failed: {}", .0, .1)]
+    NodeRequestError(String, #[source] Box<dyn std::error::Error>),
+
+    #[error("could not impersonate account {:#x}: {}", .0, .1)]
+    ImpersonationError(Address, #[source] Box<dyn std::error::Error>),
+
+    #[error("could not set fees: {}", .0)]
+    FeeError(String),
 }
 
 pub type ContractInstanceType = ContractInstance<
-    Arc<SignerMiddleware<ethers_providers::Provider<Ws>, Wallet<ecdsa::SigningKey<Secp256k1>>>>,
-    SignerMiddleware<ethers_providers::Provider<Ws>, Wallet<ecdsa::SigningKey<Secp256k1>>>,
+    Arc<NonceManager<SignerMiddleware<Provider<Transport>, Wallet<ecdsa::SigningKey<Secp256k1>>>>>,
+    NonceManager<SignerMiddleware<Provider<Transport>, Wallet<ecdsa::SigningKey<Secp256k1>>>>,
 >;
 
-pub type EthClient = Arc<SignerMiddleware<Provider<Ws>, LocalWallet>>;
+pub type EthClient = Arc<NonceManager<SignerMiddleware<Provider<Transport>, LocalWallet>>>;
+
+/// Name, ABI and bytecode of a compiled contract.
+pub type Artifact = (String, Abi, Bytes);
 
 #[derive(Clone)]
 pub struct EthereumClient {
     client: EthClient,
+    signers: Vec<EthClient>,
+    relayer: EthClient,
     contracts: CompilerOutput,
+    chain_id: u64,
+    endpoint: String,
+    impersonation: bool,
+    fee_strategy: FeeStrategy,
+    confirmations: u64,
+    impersonation_locks: Arc<Mutex<HashMap<Address, Arc<Mutex<()>>>>>,
 }
 
 impl EthereumClient {
@@ -71,37 +128,153 @@ impl EthereumClient {
             )
         })?;
 
-        let provider = get_env_var(ENDPOINT)
-            .map(Provider::<Ws>::connect)
-            // .map(Provider::<Http>::try_from)?
-            .map_err(|e| {
-                EthereumClientError::ClientInitError(
-                    "could not connect to endpoint".to_owned(),
-                    e.into(),
-                )
-            })?
-            .await
-            .unwrap();
-        // })?;
+        let endpoint = get_env_var(ENDPOINT)?;
+        let provider = Transport::provider(&endpoint).await.map_err(|e| {
+            EthereumClientError::ClientInitError(
+                format!("could not connect to endpoint {endpoint}: {e}"),
+                e.into(),
+            )
+        })?;
+        log::info!("connected to {endpoint} over {}", provider.as_ref().name());
 
-        let wallet = get_env_var(PRIVATE_KEY)
-            .map(|pk| pk[2..].parse::<LocalWallet>())?
-            .map_err(|e| {
+        let fee_strategy = FeeStrategy::from_env().map_err(|e| {
+            EthereumClientError::ClientInitError(format!("invalid fee strategy: {e}"), e.into())
+        })?;
+
+        let confirmations = match get_env_var(CONFIRMATIONS) {
+            Ok(confirmations) => confirmations.parse::<u64>().ok().filter(|c| *c > 0).ok_or(
                 EthereumClientError::ClientInitError(
-                    "could not parse private key".to_owned(),
-                    e.into(),
-                )
-            })?;
+                    format!("{CONFIRMATIONS} has to be a number of at least 1"),
+                    confirmations.into(),
+                ),
+            )?,
+            Err(_) => DEFAULT_CONFIRMATIONS,
+        };
+
+        let mnemonic_wallets = EthereumClient::mnemonic_wallets()?;
+        let (wallet, source) = match (get_env_var(KEYSTORE), get_env_var(PRIVATE_KEY)) {
+            (Ok(keystore), _) => (keystore::load_wallet(&keystore)?, KEYSTORE),
+            (_, Ok(pk)) => (keystore::parse_private_key(&pk)?, PRIVATE_KEY),
+            (_, Err(e)) => match mnemonic_wallets.first() {
+                Some(wallet) => (wallet.clone(), MNEMONIC),
+                None => return Err(e.into()),
+            },
+        };
+        // a key next to a mnemonic has to belong to it, otherwise the signers would be mixed up
+        if !mnemonic_wallets.is_empty()
+            && !mnemonic_wallets
+                .iter()
+                .any(|w| w.address() == wallet.address())
+        {
+            return Err(EthereumClientError::MnemonicMismatch(
+                wallet.address(),
+                source.to_owned(),
+                mnemonic_wallets.len(),
+            ));
+        }
 
-        let wallet_with_chain_id = wallet.with_chain_id(chain_id);
-        let client = SignerMiddleware::new(provider, wallet_with_chain_id);
+        if let Ok(account) = get_env_var(ACCOUNT) {
+            let account = helper::parse_address(&account)?;
+            if account != wallet.address() {
+                return Err(EthereumClientError::AccountMismatch(
+                    account,
+                    wallet.address(),
+                ));
+            }
+        }
+
+        let mut wallets = vec![wallet];
+        wallets.extend(EthereumClient::other_wallets()?);
+        wallets.extend(mnemonic_wallets);
+
+        let mut signers: Vec<EthClient> = vec![];
+        for wallet in wallets {
+            if signers.iter().any(|s| s.address() == wallet.address()) {
+                continue;
+            }
+            let wallet_with_chain_id = wallet.with_chain_id(chain_id);
+            let client = SignerMiddleware::new(provider.clone(), wallet_with_chain_id);
+            signers.push(std::sync::Arc::new(NonceManager::new(client)));
+        }
+
+        // relays meta transactions, the last signer unless configured
+        let relayer = match get_env_var(RELAYER) {
+            Ok(relayer) => {
+                let address = helper::parse_address(&relayer)?;
+                signers
+                    .iter()
+                    .find(|s| s.address() == address)
+                    .cloned()
+                    .ok_or(EthereumClientError::ClientInitError(
+                        format!("{RELAYER} {address:#x} is not a configured signer"),
+                        relayer.into(),
+                    ))?
+            }
+            Err(_) => signers[signers.len() - 1].clone(),
+        };
 
         EthereumClient::compile_contracts().map(|contracts| EthereumClient {
-            client: std::sync::Arc::new(client),
+            client: signers[0].clone(),
+            signers,
+            relayer,
             contracts,
+            chain_id,
+            endpoint,
+            impersonation: get_env_var(IMPERSONATION).is_ok_and(|v| v == "true"),
+            fee_strategy,
+            confirmations,
+            impersonation_locks: Default::default(),
         })
     }
 
+    fn other_wallets() -> Result<Vec<LocalWallet>, EthereumClientError> {
+        let mut wallets = vec![];
+
+        if let Ok(keys) = get_env_var(OTHER_PRIVATE_KEYS) {
+            for pk in keys.split_whitespace() {
+                wallets.push(keystore::parse_private_key(pk)?);
+            }
+        }
+
+        Ok(wallets)
+    }
+
+    /// Derives the wallets `<path>/0` to `<path>/<count - 1>` of the configured mnemonic.
+    fn mnemonic_wallets() -> Result<Vec<LocalWallet>, EthereumClientError> {
+        let phrase = match get_env_var(MNEMONIC) {
+            Ok(phrase) => phrase,
+            Err(_) => return Ok(vec![]),
+        };
+        let path =
+            get_env_var(MNEMONIC_DERIVATION_PATH).unwrap_or(DEFAULT_DERIVATION_PATH.to_owned());
+        let count = match get_env_var(MNEMONIC_ACCOUNT_COUNT) {
+            Ok(count) => count.parse::<u32>().map_err(|e| {
+                EthereumClientError::ClientInitError(
+                    "mnemonic account count could not be parsed".to_owned(),
+                    e.into(),
+                )
+            })?,
+            Err(_) => DEFAULT_MNEMONIC_ACCOUNT_COUNT,
+        };
+
+        let mut wallets = vec![];
+        for index in 0..count {
+            let wallet = MnemonicBuilder::<English>::default()
+                .phrase(phrase.as_str())
+                .derivation_path(&format!("{}/{index}", path.trim_end_matches('/')))
+                .and_then(|builder| builder.build())
+                .map_err(|e| {
+                    EthereumClientError::ClientInitError(
+                        "could not derive wallet from mnemonic".to_owned(),
+                        e.into(),
+                    )
+                })?;
+            wallets.push(wallet);
+        }
+
+        Ok(wallets)
+    }
+
     fn compile_contracts() -> Result<CompilerOutput, EthereumClientError> {
         let source = get_env_var(CONTRACTS_PATH)
             .map(|path| Path::new(&path).canonicalize())?
@@ -131,6 +304,313 @@ impl EthereumClient {
         self.client.clone()
     }
 
+    pub fn signer_addresses(&self) -> Vec<Address> {
+        self.signers.iter().map(|s| s.address()).collect()
+    }
+
+    /// Returns the client signing with the given account, or the default client if `None`.
+    pub fn get_signer_client(&self, address: Option<Address>) -> Option<EthClient> {
+        match address {
+            Some(address) => self
+                .signers
+                .iter()
+                .find(|s| s.address() == address)
+                .cloned(),
+            None => Some(self.client.clone()),
+        }
+    }
+
+    /// Signer which submits meta transactions signed by other accounts and pays their gas.
+    pub fn relayer(&self) -> EthClient {
+        self.relayer.clone()
+    }
+
+    /// Whether transactions may be sent from arbitrary addresses via `anvil_impersonateAccount`.
+    pub fn impersonation_enabled(&self) -> bool {
+        self.impersonation
+    }
+
+    async fn impersonate(&self, address: Address) -> Result<(), EthereumClientError> {
+        self.client
+            .provider()
+            .request::<_, ()>("anvil_impersonateAccount", [address])
+            .await
+            .map_err(|e| EthereumClientError::ImpersonationError(address, e.into()))
+    }
+
+    async fn stop_impersonating(&self, address: Address) -> Result<(), EthereumClientError> {
+        self.client
+            .provider()
+            .request::<_, ()>("anvil_stopImpersonatingAccount", [address])
+            .await
+            .map_err(|e| EthereumClientError::ImpersonationError(address, e.into()))
+    }
+
+    /// Runs `send` while the address is impersonated. Sends of the same address are serialised so
+    /// that one does not stop the impersonation while another is still sending.
+    pub async fn impersonated<T>(
+        &self,
+        address: Address,
+        send: impl Future<Output = T>,
+    ) -> Result<T, EthereumClientError> {
+        let lock = self
+            .impersonation_locks
+            .lock()
+            .await
+            .entry(address)
+            .or_default()
+            .clone();
+        let _guard = lock.lock().await;
+
+        self.impersonate(address).await?;
+        let result = send.await;
+        if let Err(e) = self.stop_impersonating(address).await {
+            log::error!("{e}");
+        }
+        Ok(result)
+    }
+
+    /// Takes a snapshot of the chain state via `evm_snapshot` and returns its id.
+    pub async fn snapshot(&self) -> Result<U256, EthereumClientError> {
+        self.node_request("evm_snapshot", ()).await
+    }
+
+    /// Reverts the chain via `evm_revert` and resyncs the signers' nonces, which it reverts too.
+    pub async fn revert_to_snapshot(&self, id: U256) -> Result<bool, EthereumClientError> {
+        let reverted = self.node_request("evm_revert", [id]).await?;
+        if reverted {
+            for signer in self.signers.iter() {
+                signer.resync().await;
+            }
+        }
+        Ok(reverted)
+    }
+
+    /// Replaces the last `depth` blocks with empty ones (`anvil_reorg`), dropping their
+    /// transactions.
+    pub async fn reorg(&self, depth: u64) -> Result<(), EthereumClientError> {
+        let no_transactions: [(); 0] = [];
+        self.node_request::<_, serde_json::Value>("anvil_reorg", (depth, no_transactions))
+            .await?;
+        for signer in self.signers.iter() {
+            signer.resync().await;
+        }
+        Ok(())
+    }
+
+    pub async fn mine(&self) -> Result<(), EthereumClientError> {
+        self.node_request::<_, U256>("evm_mine", ())
+            .await
+            .map(|_| ())
+    }
+
+    /// Moves the node time forward and returns the total time offset in seconds.
+    pub async fn increase_time(&self, seconds: u64) -> Result<i64, EthereumClientError> {
+        self.node_request("evm_increaseTime", [U256::from(seconds)])
+            .await
+    }
+
+    pub async fn set_next_block_timestamp(
+        &self,
+        timestamp: u64,
+    ) -> Result<(), EthereumClientError> {
+        self.node_request::<_, serde_json::Value>("evm_setNextBlockTimestamp", [timestamp])
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn automine(&self) -> Result<bool, EthereumClientError> {
+        self.node_request("anvil_getAutomine", ()).await
+    }
+
+    pub async fn set_automine(&self, enabled: bool) -> Result<(), EthereumClientError> {
+        self.node_request::<_, serde_json::Value>("evm_setAutomine", [enabled])
+            .await
+            .map(|_| ())
+    }
+
+    /// Mines a block every `seconds`; `0` disables interval mining.
+    pub async fn set_interval_mining(&self, seconds: u64) -> Result<(), EthereumClientError> {
+        self.node_request::<_, serde_json::Value>("evm_setIntervalMining", [seconds])
+            .await
+            .map(|_| ())
+    }
+
+    /// Traces the call tree of the transaction with the `callTracer`.
+    pub async fn trace_calls(&self, tx_hash: H256) -> Result<CallFrame, EthereumClientError> {
+        let options = serde_json::json!({ "tracer": "callTracer" });
+        self.node_request("debug_traceTransaction", (tx_hash, options))
+            .await
+    }
+
+    /// Traces the executed opcodes (struct logs) of the transaction without storage and memory.
+    pub async fn trace_steps(&self, tx_hash: H256) -> Result<DefaultFrame, EthereumClientError> {
+        let options = serde_json::json!({
+            "disableStorage": true,
+            "disableStack": true,
+            "enableMemory": false,
+        });
+        self.node_request("debug_traceTransaction", (tx_hash, options))
+            .await
+    }
+
+    /// Traces the call tree of the transaction simulated on the latest block.
+    pub async fn trace_call(
+        &self,
+        tx: &TypedTransaction,
+    ) -> Result<CallFrame, EthereumClientError> {
+        let options = serde_json::json!({ "tracer": "callTracer" });
+        self.node_request("debug_traceCall", (tx, BlockNumber::Latest, options))
+            .await
+    }
+
+    pub fn decode_revert(&self, data: &[u8]) -> Revert {
+        revert::decode(&self.contract_artifacts(), data)
+    }
+
+    /// Decodes why the call failed, following the revert to the nested call it originated from
+    /// if the node supports tracing, or else from the revert data returned by the node.
+    pub async fn call_revert(&self, tx: &TypedTransaction, data: Option<&Bytes>) -> Revert {
+        let artifacts = self.contract_artifacts();
+        match self.trace_call(tx).await {
+            Ok(frame) => {
+                if let Some(revert) = revert::decode_frame(&artifacts, &frame) {
+                    return revert;
+                }
+            }
+            Err(e) => log::debug!("could not trace reverted call: {e}"),
+        }
+        revert::decode(&artifacts, data.map(|d| d.as_ref()).unwrap_or_default())
+    }
+
+    /// Decodes why the mined transaction failed from its call trace.
+    pub async fn transaction_revert(&self, tx_hash: H256) -> Revert {
+        match self.trace_calls(tx_hash).await {
+            Ok(frame) => {
+                revert::decode_frame(&self.contract_artifacts(), &frame).unwrap_or(Revert::Empty)
+            }
+            Err(e) => {
+                log::debug!("could not trace failed transaction: {e}");
+                Revert::Empty
+            }
+        }
+    }
+
+    async fn node_request<P, R>(&self, method: &str, params: P) -> Result<R, EthereumClientError>
+    where
+        P: Serialize + Debug + Send + Sync,
+        R: Serialize + DeserializeOwned + Debug + Send,
+    {
+        self.client
+            .provider()
+            .request::<P, R>(method, params)
+            .await
+            .map_err(|e| EthereumClientError::NodeRequestError(method.to_owned(), e.into()))
+    }
+
+    /// Sets the gas limit and fees of the transaction from the overrides, filling in the fees
+    /// which are not overridden with the configured fee strategy.
+    pub async fn apply_fees(
+        &self,
+        tx: &mut TypedTransaction,
+        overrides: &FeeOverrides,
+    ) -> Result<AppliedFees, EthereumClientError> {
+        fees::apply(&self.client, &self.fee_strategy, tx, overrides)
+            .await
+            .map_err(EthereumClientError::FeeError)
+    }
+
+    pub fn fee_strategy(&self) -> &FeeStrategy {
+        &self.fee_strategy
+    }
+
+    /// Default number of blocks, including the one of the receipt, after which a transaction
+    /// counts as final.
+    pub fn confirmations(&self) -> u64 {
+        self.confirmations
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    pub fn transport(&self) -> &Transport {
+        self.client.provider().as_ref()
+    }
+
+    pub fn address(&self) -> Address {
+        self.client.address()
+    }
+
+    pub fn bytecode_hash(&self, contract_name: &str) -> Result<H256, EthereumClientError> {
+        match self.contracts.find(contract_name) {
+            Some(compiled) => {
+                let (_abi, bytecode, _runtime_bytecode) = compiled.into_parts_or_default();
+                Ok(H256::from(keccak256(bytecode)))
+            }
+            None => Err(EthereumClientError::ContractNotFound(
+                contract_name.to_owned(),
+            )),
+        }
+    }
+
+    pub fn contract_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .contracts
+            .contracts_iter()
+            .filter(|(_, contract)| {
+                contract
+                    .abi
+                    .as_ref()
+                    .map(|abi| !abi.abi.functions.is_empty())
+                    .unwrap_or(false)
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Returns the name, ABI and bytecode of every compiled contract with an ABI.
+    pub fn contract_artifacts(&self) -> Vec<Artifact> {
+        self.contract_names()
+            .into_iter()
+            .filter_map(|name| {
+                let (abi, bytecode, _runtime_bytecode) =
+                    self.contracts.find(&name)?.into_parts_or_default();
+                Some((name, abi, bytecode))
+            })
+            .collect()
+    }
+
+    pub fn contract_abi(&self, contract_name: &str) -> Result<Abi, EthereumClientError> {
+        match self.contracts.find(contract_name).and_then(|c| c.abi) {
+            Some(abi) => Ok(abi.clone()),
+            None => Err(EthereumClientError::ContractNotFound(
+                contract_name.to_owned(),
+            )),
+        }
+    }
+
+    /// Whether the constructor of the contract accepts ether, which the parsed ABI does not keep.
+    pub fn constructor_payable(&self, contract_name: &str) -> bool {
+        self.contracts
+            .contracts_iter()
+            .find(|(name, _)| *name == contract_name)
+            .and_then(|(_, contract)| contract.abi.as_ref())
+            .and_then(|abi| abi.abi_value.as_array())
+            .is_some_and(|items| {
+                items.iter().any(|item| {
+                    item["type"] == "constructor" && item["stateMutability"] == "payable"
+                })
+            })
+    }
+
     pub async fn contract_from_address(
         &self,
         contract_name: &str,
@@ -153,7 +633,8 @@ impl EthereumClient {
         &self,
         contract_name: &str,
         constructor_args: T,
-    ) -> Result<ContractInstanceType, EthereumClientError> {
+        value: U256,
+    ) -> Result<(ContractInstanceType, TransactionReceipt), EthereumClientError> {
         let (abi, bytecode, _runtime_bytecode) = match self.contracts.find(contract_name) {
             Some(compiled) => compiled.into_parts_or_default(),
             None => {
@@ -165,13 +646,20 @@ impl EthereumClient {
 
         let factory = ContractFactory::new(abi, bytecode, self.client.clone());
 
-        let contract = factory
+        let mut deployer = factory
             .deploy(constructor_args)
-            .map_err(|e| EthereumClientError::DeployerCreationError(e.into()))?
-            .confirmations(0usize)
-            .send()
+            .map_err(|e| EthereumClientError::DeployerCreationError(e.into()))?;
+        deployer.tx.set_value(value);
+        let contract = deployer
+            .confirmations(self.confirmations as usize)
+            .send_with_receipt()
             .await;
 
-        contract.map_err(|e| EthereumClientError::ContractDeploymentError(e.into()))
+        contract.map_err(|e| match e.as_revert() {
+            Some(data) => {
+                EthereumClientError::ContractDeploymentError(self.decode_revert(data).into())
+            }
+            None => EthereumClientError::ContractDeploymentError(e.into()),
+        })
     }
 }
@@ -0,0 +1,159 @@
+use crate::client::ethereumclient::EthClient;
+use ethers::types::{BlockNumber, H256, U256, U64};
+use ethers_contract::{Contract, EthEvent, LogMeta};
+use ethers_providers::Middleware;
+use futures::{stream::LocalBoxStream, StreamExt};
+use std::{fmt::Debug, time::Duration};
+
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Streams the events of type `T` to `handle`, subscribed to if the transport supports it or
+/// polled otherwise. When the stream ends, e.g. because the node restarted, it is resumed once
+/// the node is reachable again and the events emitted in between are replayed from the last
+/// seen block, skipping those already handled. If the chain went back, e.g. after reverting to a
+/// snapshot or a reorg, the events mined at lower positions are handled again.
+pub async fn watch<T, F>(eth_client: EthClient, mut handle: F)
+where
+    T: EthEvent + Debug + Clone,
+    F: FnMut(T, LogMeta),
+{
+    let mut last_seen: Option<Position> = None;
+    loop {
+        if last_seen.is_none() {
+            match eth_client.get_block(BlockNumber::Latest).await {
+                Ok(Some(block)) => {
+                    last_seen = Some(Position {
+                        block_number: block.number.unwrap_or_default(),
+                        block_hash: block.hash.unwrap_or_default(),
+                        log_index: U256::MAX,
+                    })
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    log::warn!("could not watch {} events: {e}", T::name());
+                    wait_for_connection(&eth_client).await;
+                    continue;
+                }
+            }
+        }
+
+        let event = Contract::event_of_type::<T>(eth_client.clone());
+        let stream = if eth_client.provider().as_ref().supports_pubsub() {
+            event
+                .subscribe_with_meta()
+                .await
+                .map(|stream| stream.boxed_local())
+        } else {
+            event
+                .stream_with_meta()
+                .await
+                .map(|stream| stream.boxed_local())
+        };
+        let mut stream: LocalBoxStream<_> = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::warn!("could not watch {} events: {e}", T::name());
+                wait_for_connection(&eth_client).await;
+                continue;
+            }
+        };
+
+        // replay the events emitted since the last seen block, the stream already buffers new ones
+        if let Some(seen) = last_seen {
+            // the fork point is unknown, all events are replayed and may be handled twice
+            if rewound(&eth_client, &seen).await {
+                log::warn!(
+                    "chain went back below block {} while not watching {} events, replaying all",
+                    seen.block_number,
+                    T::name()
+                );
+                last_seen = None;
+            }
+        }
+        let from_block = match last_seen {
+            Some(seen) => seen.block_number,
+            None => U64::zero(),
+        };
+        let missed = Contract::event_of_type::<T>(eth_client.clone())
+            .from_block(from_block)
+            .query_with_meta()
+            .await;
+        match missed {
+            Ok(missed) => {
+                for (event, meta) in missed {
+                    handle_new(&eth_client, event, meta, &mut last_seen, &mut handle).await;
+                }
+            }
+            Err(e) => {
+                log::warn!("could not replay missed {} events: {e}", T::name());
+                wait_for_connection(&eth_client).await;
+                continue;
+            }
+        }
+
+        while let Some(item) = stream.next().await {
+            match item {
+                Ok((event, meta)) => {
+                    handle_new(&eth_client, event, meta, &mut last_seen, &mut handle).await
+                }
+                Err(e) => log::error!("error receiving {} event: {e}", T::name()),
+            }
+        }
+        log::warn!("{} event stream ended, resubscribing", T::name());
+        wait_for_connection(&eth_client).await;
+    }
+}
+
+/// Position of the last handled event.
+#[derive(Clone, Copy)]
+struct Position {
+    block_number: U64,
+    block_hash: H256,
+    log_index: U256,
+}
+
+/// Handles the event unless it was already handled, i.e. it is not after the last seen one while
+/// the block of the last seen one is still part of the chain.
+async fn handle_new<T, F>(
+    eth_client: &EthClient,
+    event: T,
+    meta: LogMeta,
+    last_seen: &mut Option<Position>,
+    handle: &mut F,
+) where
+    F: FnMut(T, LogMeta),
+{
+    if let Some(seen) = last_seen {
+        let handled = (meta.block_number, meta.log_index) <= (seen.block_number, seen.log_index);
+        if handled && !rewound(eth_client, seen).await {
+            return;
+        }
+    }
+    *last_seen = Some(Position {
+        block_number: meta.block_number,
+        block_hash: meta.block_hash,
+        log_index: meta.log_index,
+    });
+    handle(event, meta);
+}
+
+/// Whether the block of the position was reverted or replaced by a reorg.
+async fn rewound(eth_client: &EthClient, position: &Position) -> bool {
+    match eth_client.get_block(position.block_number).await {
+        Ok(Some(block)) => block.hash != Some(position.block_hash),
+        Ok(None) => true,
+        Err(e) => {
+            log::warn!("could not load block {}: {e}", position.block_number);
+            false
+        }
+    }
+}
+
+async fn wait_for_connection(eth_client: &EthClient) {
+    loop {
+        actix_web::rt::time::sleep(RETRY_INTERVAL).await;
+        if eth_client.get_block_number().await.is_ok() {
+            return;
+        }
+    }
+}
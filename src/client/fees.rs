@@ -0,0 +1,304 @@
+use crate::helper::get_env_var;
+use ethers::{
+    types::{
+        transaction::eip2718::TypedTransaction, BlockNumber, Eip1559TransactionRequest,
+        TransactionRequest, U256,
+    },
+    utils::{format_units, parse_units},
+};
+use ethers_providers::Middleware;
+use serde::Serialize;
+use std::fmt::Display;
+
+const FEE_STRATEGY: &str = "FEE_STRATEGY";
+const MAX_FEE_PER_GAS: &str = "MAX_FEE_PER_GAS";
+const MAX_PRIORITY_FEE_PER_GAS: &str = "MAX_PRIORITY_FEE_PER_GAS";
+const FEE_HISTORY_BLOCKS: &str = "FEE_HISTORY_BLOCKS";
+const FEE_HISTORY_PERCENTILE: &str = "FEE_HISTORY_PERCENTILE";
+const DEFAULT_FEE_HISTORY_BLOCKS: u64 = 10;
+const DEFAULT_FEE_HISTORY_PERCENTILE: f64 = 50.0;
+
+/// How the fees of a transaction are chosen when they are not set in the form.
+#[derive(Debug, Clone)]
+pub enum FeeStrategy {
+    /// Fixed max fee and priority fee from the environment.
+    Fixed {
+        max_fee_per_gas: U256,
+        max_priority_fee_per_gas: U256,
+    },
+    /// Priority fee suggested by the node (`eth_maxPriorityFeePerGas`).
+    Node,
+    /// Priority fee at a percentile of the rewards paid in recent blocks (`eth_feeHistory`).
+    Percentile { blocks: u64, percentile: f64 },
+}
+
+/// Fee fields of a transaction form, empty ones are left to the fee strategy.
+#[derive(Debug, Clone, Default)]
+pub struct FeeOverrides {
+    pub gas_limit: Option<U256>,
+    pub max_fee_per_gas: Option<U256>,
+    pub max_priority_fee_per_gas: Option<U256>,
+    pub gas_price: Option<U256>,
+}
+
+/// Fees set on a transaction before it was sent.
+#[derive(Debug, Clone, Serialize)]
+pub struct AppliedFees {
+    pub gas_limit: Option<String>,
+    pub max_fee_per_gas: Option<String>,
+    pub max_priority_fee_per_gas: Option<String>,
+    pub gas_price: Option<String>,
+    pub source: String,
+}
+
+impl FeeStrategy {
+    /// Reads `FEE_STRATEGY` (`node`, `fixed` or `percentile`), defaulting to `node`.
+    pub fn from_env() -> Result<Self, String> {
+        match get_env_var(FEE_STRATEGY).as_deref().unwrap_or("node") {
+            "node" => Ok(FeeStrategy::Node),
+            "fixed" => Ok(FeeStrategy::Fixed {
+                max_fee_per_gas: env_gwei(MAX_FEE_PER_GAS)?,
+                max_priority_fee_per_gas: env_gwei(MAX_PRIORITY_FEE_PER_GAS)?,
+            }),
+            "percentile" => {
+                let blocks = match get_env_var(FEE_HISTORY_BLOCKS) {
+                    Ok(blocks) => blocks
+                        .parse::<u64>()
+                        .map_err(|e| format!("invalid {FEE_HISTORY_BLOCKS}: {e}"))?,
+                    Err(_) => DEFAULT_FEE_HISTORY_BLOCKS,
+                };
+                let percentile = match get_env_var(FEE_HISTORY_PERCENTILE) {
+                    Ok(percentile) => percentile
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|p| (0.0..=100.0).contains(p))
+                        .ok_or(format!(
+                            "invalid {FEE_HISTORY_PERCENTILE}: expected 0 to 100"
+                        ))?,
+                    Err(_) => DEFAULT_FEE_HISTORY_PERCENTILE,
+                };
+                if blocks == 0 {
+                    return Err(format!("invalid {FEE_HISTORY_BLOCKS}: expected at least 1"));
+                }
+                Ok(FeeStrategy::Percentile { blocks, percentile })
+            }
+            other => Err(format!(
+                "invalid {FEE_STRATEGY} {other}: expected node, fixed or percentile"
+            )),
+        }
+    }
+
+    /// Returns the max fee and priority fee per gas for the next block. The max fee leaves room
+    /// for the base fee to double.
+    pub async fn estimate<M: Middleware>(&self, client: &M) -> Result<(U256, U256), String> {
+        let (base_fee, priority_fee) = match self {
+            FeeStrategy::Fixed {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            } => return Ok((*max_fee_per_gas, *max_priority_fee_per_gas)),
+            FeeStrategy::Node => {
+                let block = client
+                    .get_block(BlockNumber::Latest)
+                    .await
+                    .map_err(|e| e.to_string())?
+                    .ok_or("latest block not found")?;
+                let priority_fee = client
+                    .provider()
+                    .request::<_, U256>("eth_maxPriorityFeePerGas", ())
+                    .await
+                    .map_err(|e| e.to_string())?;
+                let base_fee = block
+                    .base_fee_per_gas
+                    .ok_or("the node does not support EIP-1559 fees, set a gas price instead")?;
+                (base_fee, priority_fee)
+            }
+            FeeStrategy::Percentile { blocks, percentile } => {
+                let history = client
+                    .fee_history(*blocks, BlockNumber::Latest, &[*percentile])
+                    .await
+                    .map_err(|e| e.to_string())?;
+                // the last base fee is the one of the next block
+                let base_fee =
+                    history.base_fee_per_gas.last().copied().ok_or(
+                        "the node does not support EIP-1559 fees, set a gas price instead",
+                    )?;
+                let rewards: Vec<U256> = history
+                    .reward
+                    .iter()
+                    .filter_map(|reward| reward.first().copied())
+                    .collect();
+                let priority_fee = match rewards.len() {
+                    0 => U256::zero(),
+                    n => rewards.iter().fold(U256::zero(), |sum, r| sum + r) / n,
+                };
+                (base_fee, priority_fee)
+            }
+        };
+        Ok((base_fee * 2 + priority_fee, priority_fee))
+    }
+}
+
+impl Display for FeeStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FeeStrategy::Fixed { .. } => write!(f, "fixed"),
+            FeeStrategy::Node => write!(f, "node suggestion"),
+            FeeStrategy::Percentile { blocks, percentile } => {
+                write!(f, "{percentile}th percentile of {blocks} blocks")
+            }
+        }
+    }
+}
+
+impl FeeOverrides {
+    /// Parses the gas limit and the fees in gwei, empty fields are ignored.
+    pub fn parse(
+        gas_limit: Option<&str>,
+        max_fee_per_gas: Option<&str>,
+        max_priority_fee_per_gas: Option<&str>,
+        gas_price: Option<&str>,
+    ) -> Result<Self, String> {
+        let overrides = FeeOverrides {
+            gas_limit: match non_empty(gas_limit) {
+                Some(gas) => {
+                    Some(U256::from_dec_str(gas).map_err(|e| format!("invalid gas limit: {e}"))?)
+                }
+                None => None,
+            },
+            max_fee_per_gas: parse_gwei(max_fee_per_gas, "max fee")?,
+            max_priority_fee_per_gas: parse_gwei(max_priority_fee_per_gas, "priority fee")?,
+            gas_price: parse_gwei(gas_price, "gas price")?,
+        };
+        if overrides.gas_price.is_some()
+            && (overrides.max_fee_per_gas.is_some() || overrides.max_priority_fee_per_gas.is_some())
+        {
+            return Err("a legacy gas price cannot be combined with EIP-1559 fees".to_owned());
+        }
+        Ok(overrides)
+    }
+}
+
+/// Sets the gas limit and fees of the transaction, turning it into a legacy transaction (unless it
+/// is an EIP-2930 one) if a gas price is given and into an EIP-1559 one otherwise.
+pub async fn apply<M: Middleware>(
+    client: &M,
+    strategy: &FeeStrategy,
+    tx: &mut TypedTransaction,
+    overrides: &FeeOverrides,
+) -> Result<AppliedFees, String> {
+    let from = tx.from().copied();
+    let mut applied = AppliedFees {
+        gas_limit: overrides.gas_limit.map(|gas| gas.to_string()),
+        max_fee_per_gas: None,
+        max_priority_fee_per_gas: None,
+        gas_price: None,
+        source: "form".to_owned(),
+    };
+
+    if let Some(gas_price) = overrides.gas_price {
+        match tx {
+            // keeps the access list
+            TypedTransaction::Eip2930(eip2930) => eip2930.tx.gas_price = Some(gas_price),
+            _ => {
+                let mut legacy: TransactionRequest = tx.clone().into();
+                legacy.gas_price = Some(gas_price);
+                *tx = legacy.into();
+            }
+        }
+        applied.gas_price = Some(gwei(gas_price));
+    } else {
+        let (max_fee, priority_fee) = match (
+            overrides.max_fee_per_gas,
+            overrides.max_priority_fee_per_gas,
+        ) {
+            (Some(max_fee), Some(priority_fee)) => (max_fee, priority_fee),
+            (max_fee, priority_fee) => {
+                let (estimated_max_fee, estimated_priority_fee) = strategy.estimate(client).await?;
+                applied.source = match max_fee.or(priority_fee) {
+                    Some(_) => format!("form and {strategy}"),
+                    None => strategy.to_string(),
+                };
+                match (max_fee, priority_fee) {
+                    (Some(max_fee), None) => (max_fee, estimated_priority_fee.min(max_fee)),
+                    // keep the headroom for the base fee on top of the given priority fee
+                    (None, Some(priority_fee)) => (
+                        estimated_max_fee.saturating_sub(estimated_priority_fee) + priority_fee,
+                        priority_fee,
+                    ),
+                    _ => (estimated_max_fee, estimated_priority_fee),
+                }
+            }
+        };
+        if max_fee < priority_fee {
+            return Err(format!(
+                "max fee {} gwei is below the priority fee {} gwei",
+                gwei(max_fee),
+                gwei(priority_fee)
+            ));
+        }
+        let mut eip1559: Eip1559TransactionRequest = tx.clone().into();
+        eip1559.max_fee_per_gas = Some(max_fee);
+        eip1559.max_priority_fee_per_gas = Some(priority_fee);
+        *tx = eip1559.into();
+        applied.max_fee_per_gas = Some(gwei(max_fee));
+        applied.max_priority_fee_per_gas = Some(gwei(priority_fee));
+    }
+
+    if let Some(gas_limit) = overrides.gas_limit {
+        tx.set_gas(gas_limit);
+    }
+    if let Some(from) = from {
+        tx.set_from(from);
+    }
+    Ok(applied)
+}
+
+impl Display for AppliedFees {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut fees = vec![];
+        if let Some(gas_limit) = &self.gas_limit {
+            fees.push(format!("gas limit {gas_limit}"));
+        }
+        if let Some(gas_price) = &self.gas_price {
+            fees.push(format!("gas price {gas_price} gwei"));
+        }
+        if let Some(max_fee) = &self.max_fee_per_gas {
+            fees.push(format!("max fee {max_fee} gwei"));
+        }
+        if let Some(priority_fee) = &self.max_priority_fee_per_gas {
+            fees.push(format!("priority fee {priority_fee} gwei"));
+        }
+        write!(f, "{} ({})", fees.join(", "), self.source)
+    }
+}
+
+/// Formats wei as gwei without trailing zeros.
+pub fn gwei(wei: U256) -> String {
+    let formatted = format_units(wei, "gwei").unwrap_or_else(|_| wei.to_string());
+    match formatted.contains('.') {
+        true => formatted
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_owned(),
+        false => formatted,
+    }
+}
+
+/// Parses a fee in gwei, empty values are `None`.
+pub fn parse_gwei(value: Option<&str>, name: &str) -> Result<Option<U256>, String> {
+    match non_empty(value) {
+        Some(value) => parse_units(value, "gwei")
+            .map(|wei| Some(wei.into()))
+            .map_err(|e| format!("invalid {name} {value}: {e}")),
+        None => Ok(None),
+    }
+}
+
+fn env_gwei(key: &str) -> Result<U256, String> {
+    let value = get_env_var(key).map_err(|e| e.to_string())?;
+    parse_gwei(Some(&value), key)?.ok_or(format!("{key} is empty"))
+}
+
+fn non_empty(value: Option<&str>) -> Option<&str> {
+    value.map(|v| v.trim()).filter(|v| !v.is_empty())
+}
@@ -0,0 +1,125 @@
+use crate::{client::ethereumclient::EthereumClientError, helper::get_env_var};
+
+use ethers::{
+    core::rand::thread_rng,
+    signers::{LocalWallet, Signer},
+};
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+const KEYSTORE_PASSWORD_FILE: &str = "KEYSTORE_PASSWORD_FILE";
+
+const USAGE: &str = "usage: keystore new <dir> [name] | keystore import <dir> [name]";
+
+/// Decrypts the JSON V3 keystore with the password of `KEYSTORE_PASSWORD_FILE` or, if not set,
+/// the password entered on the terminal.
+pub fn load_wallet(path: &str) -> Result<LocalWallet, EthereumClientError> {
+    let password = match get_env_var(KEYSTORE_PASSWORD_FILE) {
+        Ok(password_file) => fs::read_to_string(&password_file)
+            .map(|password| password.trim_end_matches(['\r', '\n']).to_owned())
+            .map_err(|e| {
+                EthereumClientError::KeystoreError(
+                    format!("could not read password file {password_file}"),
+                    e.into(),
+                )
+            })?,
+        Err(_) => read_password(&format!("Password for keystore {path}: "))?,
+    };
+
+    LocalWallet::decrypt_keystore(path, password).map_err(|e| {
+        EthereumClientError::KeystoreError(format!("could not decrypt keystore {path}"), e.into())
+    })
+}
+
+/// Runs the `keystore` command, which creates a new keystore or imports a private key into one.
+pub fn run_command(args: &[String]) -> Result<(), EthereumClientError> {
+    let (command, dir, name) = match args {
+        [command, dir] => (command.as_str(), dir.as_str(), None),
+        [command, dir, name] => (command.as_str(), dir.as_str(), Some(name.as_str())),
+        _ => return Err(usage_error()),
+    };
+    fs::create_dir_all(dir).map_err(|e| {
+        EthereumClientError::KeystoreError(format!("could not create directory {dir}"), e.into())
+    })?;
+
+    let (wallet, uuid) = match command {
+        "new" => {
+            let password = read_new_password()?;
+            LocalWallet::new_keystore(dir, &mut thread_rng(), password, name)
+        }
+        "import" => {
+            let pk = read_password("Private key: ")?;
+            let pk = parse_private_key(&pk)?;
+            let password = read_new_password()?;
+            LocalWallet::encrypt_keystore(
+                dir,
+                &mut thread_rng(),
+                pk.signer().to_bytes(),
+                password,
+                name,
+            )
+        }
+        _ => return Err(usage_error()),
+    }
+    .map_err(|e| {
+        EthereumClientError::KeystoreError("could not write keystore".to_owned(), e.into())
+    })?;
+
+    let file = Path::new(dir).join(name.unwrap_or(&uuid));
+    println!("address: {:#x}", wallet.address());
+    println!("keystore: {}", file.display());
+    Ok(())
+}
+
+pub fn parse_private_key(pk: &str) -> Result<LocalWallet, EthereumClientError> {
+    pk.trim()
+        .trim_start_matches("0x")
+        .parse::<LocalWallet>()
+        .map_err(|e| {
+            EthereumClientError::ClientInitError("could not parse private key".to_owned(), e.into())
+        })
+}
+
+fn read_new_password() -> Result<String, EthereumClientError> {
+    let password = read_password("New password: ")?;
+    if password != read_password("Repeat password: ")? {
+        return Err(EthereumClientError::KeystoreError(
+            "passwords do not match".to_owned(),
+            "password mismatch".into(),
+        ));
+    }
+    Ok(password)
+}
+
+/// Reads a line from stdin with the terminal echo turned off (if stdin is a terminal).
+fn read_password(prompt: &str) -> Result<String, EthereumClientError> {
+    eprint!("{prompt}");
+    io::stderr().flush().ok();
+
+    set_echo(false);
+    let mut line = String::new();
+    let res = io::stdin().lock().read_line(&mut line);
+    set_echo(true);
+    eprintln!();
+
+    res.map(|_| line.trim_end_matches(['\r', '\n']).to_owned())
+        .map_err(|e| {
+            EthereumClientError::KeystoreError("could not read password".to_owned(), e.into())
+        })
+}
+
+fn set_echo(on: bool) {
+    let _ = Command::new("stty")
+        .arg(if on { "echo" } else { "-echo" })
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::null())
+        .status();
+}
+
+fn usage_error() -> EthereumClientError {
+    EthereumClientError::KeystoreError(USAGE.to_owned(), "invalid arguments".into())
+}
@@ -1,3 +1,10 @@
+pub mod anvilnode;
 pub mod ethereumclient;
+pub mod events;
+pub mod fees;
+pub mod keystore;
+pub mod noncemanager;
+pub mod revert;
+pub mod transport;
 
-pub use ethereumclient::{ContractInstanceType, EthereumClient};
+pub use ethereumclient::{Artifact, ContractInstanceType, EthereumClient};
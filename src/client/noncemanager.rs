@@ -0,0 +1,149 @@
+use async_trait::async_trait;
+use ethers::{
+    middleware::SignerMiddleware,
+    signers::Signer,
+    types::{transaction::eip2718::TypedTransaction, Address, BlockId, BlockNumber, U256},
+};
+use ethers_providers::{Middleware, MiddlewareError, PendingTransaction};
+use futures::lock::Mutex;
+
+/// Hands out the nonces of the signer locally, so that several of its transactions can be in
+/// flight at once instead of racing on the node's transaction count. Transactions of other
+/// senders (e.g. impersonated ones) and with an explicit nonce are passed through.
+#[derive(Debug)]
+pub struct NonceManager<M> {
+    inner: M,
+    address: Address,
+    // next nonce to hand out, `None` until synced from the node's pending transaction count
+    next_nonce: Mutex<Option<U256>>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum NonceManagerError<M: Middleware> {
+    #[error("{}", .0)]
+    MiddlewareError(M::Error),
+}
+
+impl<M: Middleware> MiddlewareError for NonceManagerError<M> {
+    type Inner = M::Error;
+
+    fn from_err(src: M::Error) -> Self {
+        NonceManagerError::MiddlewareError(src)
+    }
+
+    fn as_inner(&self) -> Option<&Self::Inner> {
+        match self {
+            NonceManagerError::MiddlewareError(e) => Some(e),
+        }
+    }
+}
+
+impl<M: Middleware, S: Signer> NonceManager<SignerMiddleware<M, S>> {
+    pub fn new(inner: SignerMiddleware<M, S>) -> Self {
+        NonceManager {
+            address: inner.address(),
+            inner,
+            next_nonce: Mutex::new(None),
+        }
+    }
+
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    pub fn signer(&self) -> &S {
+        self.inner.signer()
+    }
+}
+
+impl<M: Middleware> NonceManager<M> {
+    /// Forgets the local nonce, e.g. after the chain was reverted to a snapshot.
+    pub async fn resync(&self) {
+        *self.next_nonce.lock().await = None;
+    }
+
+    async fn allocate(&self) -> Result<U256, NonceManagerError<M>> {
+        let mut next_nonce = self.next_nonce.lock().await;
+        let nonce = match *next_nonce {
+            Some(nonce) => nonce,
+            None => self
+                .inner
+                .get_transaction_count(self.address, Some(BlockNumber::Pending.into()))
+                .await
+                .map_err(MiddlewareError::from_err)?,
+        };
+        *next_nonce = Some(nonce + 1);
+        Ok(nonce)
+    }
+
+    /// Takes back the nonce of a transaction which was not sent. If it was the last one handed
+    /// out it is reused, otherwise the nonce is resynced to not leave a gap.
+    async fn release(&self, nonce: U256) {
+        let mut next_nonce = self.next_nonce.lock().await;
+        *next_nonce = match *next_nonce {
+            Some(next) if next == nonce + 1 => Some(nonce),
+            _ => None,
+        };
+    }
+}
+
+#[async_trait]
+impl<M: Middleware> Middleware for NonceManager<M> {
+    type Error = NonceManagerError<M>;
+    type Provider = M::Provider;
+    type Inner = M;
+
+    fn inner(&self) -> &M {
+        &self.inner
+    }
+
+    // the lifetime of `&self` gets named by `async_trait`
+    #[allow(mismatched_lifetime_syntaxes)]
+    async fn send_transaction<T: Into<TypedTransaction> + Send + Sync>(
+        &self,
+        tx: T,
+        block: Option<BlockId>,
+    ) -> Result<PendingTransaction<'_, Self::Provider>, Self::Error> {
+        let mut tx = tx.into();
+        let managed = tx.nonce().is_none() && tx.from().is_none_or(|from| *from == self.address);
+        if !managed {
+            return self
+                .inner
+                .send_transaction(tx, block)
+                .await
+                .map_err(MiddlewareError::from_err);
+        }
+
+        tx.set_from(self.address);
+        let mut resynced = false;
+        loop {
+            let nonce = self.allocate().await?;
+            tx.set_nonce(nonce);
+            match self.inner.send_transaction(tx.clone(), block).await {
+                Ok(pending_tx) => return Ok(pending_tx),
+                Err(e) if !resynced && is_nonce_error(&e) => {
+                    log::warn!(
+                        "nonce {nonce} of {:#x} rejected, resyncing: {e}",
+                        self.address
+                    );
+                    self.resync().await;
+                    resynced = true;
+                }
+                Err(e) => {
+                    self.release(nonce).await;
+                    return Err(MiddlewareError::from_err(e));
+                }
+            }
+        }
+    }
+}
+
+fn is_nonce_error<E: MiddlewareError>(e: &E) -> bool {
+    let message = match e.as_error_response() {
+        Some(response) => response.message.to_lowercase(),
+        None => e.to_string().to_lowercase(),
+    };
+    ["nonce too low", "nonce too high", "invalid nonce"]
+        .iter()
+        .any(|error| message.contains(error))
+}
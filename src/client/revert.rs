@@ -0,0 +1,134 @@
+use crate::{client::Artifact, helper};
+use ethers::{
+    abi::{self, ParamType},
+    types::{Bytes, CallFrame, NameOrAddress, U256},
+};
+
+const ERROR_STRING_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// Reason of a reverted call decoded from its revert data.
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum Revert {
+    #[error("transaction reverted without a reason")]
+    Empty,
+
+    #[error("transaction reverted: {}", .0)]
+    Error(String),
+
+    #[error("transaction panicked with code {:#x}: {}", .0, panic_reason(.0))]
+    Panic(U256),
+
+    #[error("transaction reverted with {}.{}({})", .contract, .name, .params.join(", "))]
+    Custom {
+        contract: String,
+        name: String,
+        params: Vec<String>,
+    },
+
+    #[error("unknown transaction revert error: {}", .0)]
+    Unknown(Bytes),
+
+    #[error("{} in nested call to {}", .revert, .to)]
+    Nested { revert: Box<Revert>, to: String },
+}
+
+/// Decodes `Error(string)`, `Panic(uint256)` and the custom errors of all the compiled contracts.
+pub fn decode(artifacts: &[Artifact], data: &[u8]) -> Revert {
+    if data.is_empty() {
+        return Revert::Empty;
+    }
+    if data.len() < 4 {
+        return Revert::Unknown(Bytes::from(data.to_vec()));
+    }
+    let (selector, args) = data.split_at(4);
+
+    if selector == ERROR_STRING_SELECTOR {
+        if let Some(abi::Token::String(reason)) = abi::decode(&[ParamType::String], args)
+            .ok()
+            .and_then(|tokens| tokens.into_iter().next())
+        {
+            return Revert::Error(reason);
+        }
+    }
+    if selector == PANIC_SELECTOR {
+        if let Some(abi::Token::Uint(code)) = abi::decode(&[ParamType::Uint(256)], args)
+            .ok()
+            .and_then(|tokens| tokens.into_iter().next())
+        {
+            return Revert::Panic(code);
+        }
+    }
+
+    artifacts
+        .iter()
+        .find_map(|(contract, abi, _)| {
+            abi.errors()
+                .filter(|error| error.signature()[..4] == *selector)
+                .find_map(|error| {
+                    let tokens = error.decode(args).ok()?;
+                    Some(Revert::Custom {
+                        contract: contract.clone(),
+                        name: error.name.clone(),
+                        params: error
+                            .inputs
+                            .iter()
+                            .zip(tokens.iter())
+                            .map(|(param, token)| match param.name.is_empty() {
+                                true => helper::format_token(token),
+                                false => {
+                                    format!("{}: {}", param.name, helper::format_token(token))
+                                }
+                            })
+                            .collect(),
+                    })
+                })
+        })
+        .unwrap_or(Revert::Unknown(Bytes::from(data.to_vec())))
+}
+
+/// Decodes the revert of the traced call and follows it to the nested call it was bubbled up
+/// from. If the call reverted without data, the innermost nested revert with data is used.
+pub fn decode_frame(artifacts: &[Artifact], frame: &CallFrame) -> Option<Revert> {
+    frame.error.as_ref()?;
+    let output = frame.output.clone().unwrap_or_default();
+
+    let origin = frame.calls.iter().flatten().find(|call| {
+        let call_output = call.output.clone().unwrap_or_default();
+        call.error.is_some()
+            && !call_output.is_empty()
+            && (output.is_empty() || call_output == output)
+    });
+    match origin {
+        Some(call) => match decode_frame(artifacts, call)? {
+            revert @ Revert::Nested { .. } => Some(revert),
+            revert => Some(Revert::Nested {
+                revert: Box::new(revert),
+                to: match &call.to {
+                    Some(NameOrAddress::Address(address)) => format!("{:#x}", address),
+                    Some(NameOrAddress::Name(name)) => name.clone(),
+                    None => "new contract".to_owned(),
+                },
+            }),
+        },
+        None => Some(decode(artifacts, &output)),
+    }
+}
+
+/// Describes the `Panic(uint256)` codes emitted by the Solidity compiler.
+pub fn panic_reason(code: &U256) -> &'static str {
+    match code.low_u64() {
+        _ if *code > U256::from(u64::MAX) => "unknown panic code",
+        0x00 => "generic compiler inserted panic",
+        0x01 => "assertion failed",
+        0x11 => "arithmetic overflow or underflow",
+        0x12 => "division or modulo by zero",
+        0x21 => "conversion to an invalid enum value",
+        0x22 => "incorrectly encoded storage byte array",
+        0x31 => "pop() on an empty array",
+        0x32 => "array index out of bounds",
+        0x41 => "too much memory allocated",
+        0x51 => "call to a zero-initialized internal function",
+        _ => "unknown panic code",
+    }
+}
@@ -0,0 +1,262 @@
+use async_trait::async_trait;
+use ethers::types::U256;
+use ethers_providers::{
+    is_local_endpoint, Http, HttpClientError, Ipc, IpcError, JsonRpcClient, JsonRpcError, Provider,
+    ProviderError, PubsubClient, RpcError, Ws, WsClientError, DEFAULT_LOCAL_POLL_INTERVAL,
+};
+use futures::channel::mpsc;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::value::RawValue;
+use std::{
+    fmt::Debug,
+    str::FromStr,
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
+};
+
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_millis(500);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// JSON-RPC transport chosen from the scheme of the endpoint.
+#[derive(Debug, Clone)]
+pub enum Transport {
+    Ws(ReconnectingWs),
+    Http(Http),
+    Ipc(Ipc),
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ConnectionState {
+    Connected,
+    Reconnecting { attempt: u32, error: String },
+}
+
+/// Websocket which is re-established with exponential backoff once the connection is lost.
+/// Clones share the socket, so all the providers and signers follow a reconnect.
+#[derive(Debug, Clone)]
+pub struct ReconnectingWs {
+    endpoint: String,
+    ws: Arc<RwLock<Ws>>,
+    state: Arc<Mutex<ConnectionState>>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TransportError {
+    #[error(transparent)]
+    Ws(Box<WsClientError>),
+
+    #[error(transparent)]
+    Http(#[from] HttpClientError),
+
+    #[error(transparent)]
+    Ipc(#[from] IpcError),
+
+    #[error("invalid endpoint {}: {}", .0, .1)]
+    InvalidEndpoint(String, String),
+
+    #[error("subscriptions are not supported over http")]
+    PubsubUnsupported,
+
+    #[error("connection to the node lost, reconnecting (attempt {}): {}", .0, .1)]
+    Reconnecting(u32, String),
+}
+
+impl Transport {
+    /// Connects to a `ws://`/`wss://` or `http://`/`https://` endpoint, anything else is taken as
+    /// the path of an IPC socket (optionally prefixed with `ipc://`).
+    pub async fn connect(endpoint: &str) -> Result<Self, TransportError> {
+        let endpoint = endpoint.trim();
+        let (scheme, _) = endpoint.split_once("://").unwrap_or_default();
+        match scheme {
+            "ws" | "wss" => Ok(Transport::Ws(ReconnectingWs::connect(endpoint).await?)),
+            "http" | "https" => Http::from_str(endpoint)
+                .map(Transport::Http)
+                .map_err(|e| TransportError::InvalidEndpoint(endpoint.to_owned(), e.to_string())),
+            "ipc" | "" => {
+                let path = endpoint.trim_start_matches("ipc://");
+                Ok(Transport::Ipc(Ipc::connect(path).await?))
+            }
+            _ => Err(TransportError::InvalidEndpoint(
+                endpoint.to_owned(),
+                format!("unsupported scheme {scheme}"),
+            )),
+        }
+    }
+
+    /// Creates the provider, polling filters and pending transactions quickly on a local node.
+    pub async fn provider(endpoint: &str) -> Result<Provider<Transport>, TransportError> {
+        let transport = Transport::connect(endpoint).await?;
+        let local = matches!(transport, Transport::Ipc(_)) || is_local_endpoint(endpoint);
+        let provider = Provider::new(transport);
+        Ok(match local {
+            true => provider.interval(DEFAULT_LOCAL_POLL_INTERVAL),
+            false => provider,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Transport::Ws(_) => "websocket",
+            Transport::Http(_) => "http",
+            Transport::Ipc(_) => "ipc",
+        }
+    }
+
+    /// Whether `eth_subscribe` can be used, otherwise events have to be polled with filters.
+    pub fn supports_pubsub(&self) -> bool {
+        !matches!(self, Transport::Http(_))
+    }
+
+    pub fn connection_state(&self) -> ConnectionState {
+        match self {
+            Transport::Ws(ws) => ws.state.lock().unwrap().clone(),
+            _ => ConnectionState::Connected,
+        }
+    }
+}
+
+impl ReconnectingWs {
+    async fn connect(endpoint: &str) -> Result<Self, WsClientError> {
+        // reconnects are handled here with a backoff instead of by the client itself
+        let ws = Ws::connect_with_reconnects(endpoint, 0).await?;
+        Ok(ReconnectingWs {
+            endpoint: endpoint.to_owned(),
+            ws: Arc::new(RwLock::new(ws)),
+            state: Arc::new(Mutex::new(ConnectionState::Connected)),
+        })
+    }
+
+    fn current(&self) -> Result<Ws, TransportError> {
+        match &*self.state.lock().unwrap() {
+            ConnectionState::Connected => Ok(self.ws.read().unwrap().clone()),
+            ConnectionState::Reconnecting { attempt, error } => {
+                Err(TransportError::Reconnecting(*attempt, error.clone()))
+            }
+        }
+    }
+
+    /// Starts reconnecting if the error means that the socket is gone.
+    fn check<T>(&self, result: Result<T, WsClientError>) -> Result<T, TransportError> {
+        if let Err(
+            e @ (WsClientError::UnexpectedClose
+            | WsClientError::DeadChannel
+            | WsClientError::TooManyReconnects
+            | WsClientError::InternalError(_)),
+        ) = &result
+        {
+            self.reconnect(e.to_string());
+        }
+        Ok(result?)
+    }
+
+    fn reconnect(&self, error: String) {
+        {
+            let mut state = self.state.lock().unwrap();
+            if let ConnectionState::Reconnecting { .. } = *state {
+                return;
+            }
+            log::warn!("connection to {} lost: {error}", self.endpoint);
+            *state = ConnectionState::Reconnecting { attempt: 1, error };
+        }
+
+        let this = self.clone();
+        actix_web::rt::spawn(async move {
+            let mut delay = INITIAL_RECONNECT_DELAY;
+            for attempt in 1.. {
+                actix_web::rt::time::sleep(delay).await;
+                match Ws::connect_with_reconnects(this.endpoint.as_str(), 0).await {
+                    Ok(ws) => {
+                        *this.ws.write().unwrap() = ws;
+                        *this.state.lock().unwrap() = ConnectionState::Connected;
+                        log::info!(
+                            "reconnected to {} after {attempt} attempt(s)",
+                            this.endpoint
+                        );
+                        return;
+                    }
+                    Err(e) => {
+                        log::debug!(
+                            "reconnect attempt {attempt} to {} failed: {e}",
+                            this.endpoint
+                        );
+                        *this.state.lock().unwrap() = ConnectionState::Reconnecting {
+                            attempt: attempt + 1,
+                            error: e.to_string(),
+                        };
+                        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+                    }
+                }
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for Transport {
+    type Error = TransportError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        match self {
+            Transport::Ws(ws) => ws.check(ws.current()?.request(method, params).await),
+            Transport::Http(http) => Ok(http.request(method, params).await?),
+            Transport::Ipc(ipc) => Ok(ipc.request(method, params).await?),
+        }
+    }
+}
+
+impl PubsubClient for Transport {
+    type NotificationStream = mpsc::UnboundedReceiver<Box<RawValue>>;
+
+    fn subscribe<T: Into<U256>>(&self, id: T) -> Result<Self::NotificationStream, Self::Error> {
+        match self {
+            Transport::Ws(ws) => ws.check(ws.current()?.subscribe(id)),
+            Transport::Ipc(ipc) => Ok(ipc.subscribe(id)?),
+            Transport::Http(_) => Err(TransportError::PubsubUnsupported),
+        }
+    }
+
+    fn unsubscribe<T: Into<U256>>(&self, id: T) -> Result<(), Self::Error> {
+        match self {
+            Transport::Ws(ws) => ws.check(ws.current()?.unsubscribe(id)),
+            Transport::Ipc(ipc) => Ok(ipc.unsubscribe(id)?),
+            Transport::Http(_) => Err(TransportError::PubsubUnsupported),
+        }
+    }
+}
+
+impl RpcError for TransportError {
+    fn as_error_response(&self) -> Option<&JsonRpcError> {
+        match self {
+            TransportError::Ws(e) => e.as_error_response(),
+            TransportError::Http(e) => e.as_error_response(),
+            TransportError::Ipc(e) => e.as_error_response(),
+            _ => None,
+        }
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        match self {
+            TransportError::Ws(e) => e.as_serde_error(),
+            TransportError::Http(e) => e.as_serde_error(),
+            TransportError::Ipc(e) => e.as_serde_error(),
+            _ => None,
+        }
+    }
+}
+
+impl From<WsClientError> for TransportError {
+    fn from(e: WsClientError) -> Self {
+        TransportError::Ws(Box::new(e))
+    }
+}
+
+impl From<TransportError> for ProviderError {
+    fn from(e: TransportError) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(e))
+    }
+}
@@ -0,0 +1,421 @@
+use crate::{
+    app::model::{ContractInstances, State as AppState, DEFAULT_INSTANCE_LABEL},
+    client::fees::FeeOverrides,
+    helper, lab,
+};
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use ethers::{
+    abi::{
+        token::{LenientTokenizer, Tokenizer},
+        Error as AbiError, Function, Param, StateMutability, Token,
+    },
+    types::{transaction::eip2718::TypedTransaction, TransactionRequest, U256},
+};
+use ethers_providers::{Middleware, MiddlewareError};
+use serde::Serialize;
+use std::collections::HashMap;
+use tera::Context;
+
+const SIGNATURE_FIELD: &str = "signature";
+const ADDRESS_FIELD: &str = "address";
+const VALUE_FIELD: &str = "value";
+const LABEL_FIELD: &str = "label";
+const SIGNER_FIELD: &str = "signer";
+const IMPERSONATE_FIELD: &str = "impersonate";
+const GAS_LIMIT_FIELD: &str = "gas_limit";
+const MAX_FEE_FIELD: &str = "max_fee_per_gas";
+const PRIORITY_FEE_FIELD: &str = "max_priority_fee_per_gas";
+const GAS_PRICE_FIELD: &str = "gas_price";
+const CONFIRMATIONS_FIELD: &str = "confirmations";
+
+type FormFields = HashMap<String, String>;
+
+#[derive(Serialize)]
+struct ConsoleParam {
+    name: String,
+    kind: String,
+}
+
+#[derive(Serialize)]
+struct ConsoleFunction {
+    name: String,
+    signature: String,
+    inputs: Vec<ConsoleParam>,
+    outputs: String,
+    read_only: bool,
+    payable: bool,
+}
+
+/// Lenient tokenizer which also accepts plain text for `bytesN` values (e.g. `bytes32` names).
+struct ConsoleTokenizer;
+
+impl Tokenizer for ConsoleTokenizer {
+    fn tokenize_address(value: &str) -> Result<[u8; 20], AbiError> {
+        LenientTokenizer::tokenize_address(value)
+    }
+
+    fn tokenize_string(value: &str) -> Result<String, AbiError> {
+        LenientTokenizer::tokenize_string(value)
+    }
+
+    fn tokenize_bool(value: &str) -> Result<bool, AbiError> {
+        LenientTokenizer::tokenize_bool(value)
+    }
+
+    fn tokenize_bytes(value: &str) -> Result<Vec<u8>, AbiError> {
+        LenientTokenizer::tokenize_bytes(value)
+    }
+
+    fn tokenize_fixed_bytes(value: &str, len: usize) -> Result<Vec<u8>, AbiError> {
+        LenientTokenizer::tokenize_fixed_bytes(value, len).or_else(|e| {
+            if value.len() > len {
+                return Err(e);
+            }
+            let mut bytes = value.as_bytes().to_vec();
+            bytes.resize(len, 0);
+            Ok(bytes)
+        })
+    }
+
+    fn tokenize_uint(value: &str) -> Result<[u8; 32], AbiError> {
+        LenientTokenizer::tokenize_uint(value)
+    }
+
+    fn tokenize_int(value: &str) -> Result<[u8; 32], AbiError> {
+        LenientTokenizer::tokenize_int(value)
+    }
+}
+
+pub fn setup_handlers(cfg: &mut web::ServiceConfig) {
+    cfg.service(console_handler)
+        .service(contract_handler)
+        .service(deploy_handler)
+        .service(call_handler);
+}
+
+#[get("/console")]
+async fn console_handler(app_state: web::Data<AppState>) -> impl Responder {
+    let mut context = Context::new();
+    context.insert("contract_names", &app_state.eth_client.contract_names());
+
+    match app_state.tmpl.render("console.html", &context) {
+        Ok(rendered) => HttpResponse::Ok().body(rendered),
+        Err(e) => helper::render_error(e),
+    }
+}
+
+#[get("/console/{name}")]
+async fn contract_handler(
+    name: web::Path<String>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let abi = match app_state.eth_client.contract_abi(&name) {
+        Ok(abi) => abi,
+        Err(e) => return helper::ui_alert(&e.to_string()),
+    };
+
+    let constructor_inputs = abi
+        .constructor()
+        .map(|c| to_console_params(&c.inputs))
+        .unwrap_or_default();
+    let functions: Vec<ConsoleFunction> = abi
+        .functions()
+        .map(|f| ConsoleFunction {
+            name: f.name.clone(),
+            signature: f.signature(),
+            inputs: to_console_params(&f.inputs),
+            outputs: f
+                .outputs
+                .iter()
+                .map(|p| p.kind.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            read_only: is_read_only(f),
+            payable: f.state_mutability == StateMutability::Payable,
+        })
+        .collect();
+
+    let deployed_address = app_state
+        .contracts
+        .lock()
+        .await
+        .get(name.as_str())
+        .and_then(|instances| instances.active())
+        .map(|contract| format!("{:#x}", contract.address()))
+        .unwrap_or_default();
+
+    let mut context = Context::new();
+    context.insert("contract_name", name.as_str());
+    context.insert("contract_address", &deployed_address);
+    context.insert("constructor_inputs", &constructor_inputs);
+    context.insert(
+        "constructor_payable",
+        &app_state.eth_client.constructor_payable(name.as_str()),
+    );
+    context.insert("functions", &functions);
+    context.insert("signer_addresses", &app_state.eth_client.signer_addresses());
+    context.insert(
+        "impersonation_enabled",
+        &app_state.eth_client.impersonation_enabled(),
+    );
+    context.insert(
+        "fee_strategy",
+        &app_state.eth_client.fee_strategy().to_string(),
+    );
+    context.insert("confirmations", &app_state.eth_client.confirmations());
+
+    match app_state.tmpl.render("console_contract.html", &context) {
+        Ok(rendered) => HttpResponse::Ok().body(rendered),
+        Err(e) => helper::render_error(e),
+    }
+}
+
+#[post("/console/{name}/deploy")]
+async fn deploy_handler(
+    name: web::Path<String>,
+    form: web::Form<FormFields>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let contract_name = name.as_str();
+    let abi = match app_state.eth_client.contract_abi(contract_name) {
+        Ok(abi) => abi,
+        Err(e) => return helper::ui_alert(&e.to_string()),
+    };
+    let inputs = abi
+        .constructor()
+        .map(|c| c.inputs.clone())
+        .unwrap_or_default();
+    let args = match tokenize_args(&inputs, &form) {
+        Ok(args) => args,
+        Err(e) => return helper::ui_alert(&e),
+    };
+
+    let value = match parse_value(&form) {
+        Ok(value) => value,
+        Err(e) => return helper::ui_alert(&e),
+    };
+
+    let label = match form.get(LABEL_FIELD).map(|l| l.trim()) {
+        Some(label) if !label.is_empty() => label.to_owned(),
+        _ => DEFAULT_INSTANCE_LABEL.to_owned(),
+    };
+    let exists = |lock: &HashMap<String, ContractInstances>| {
+        lock.get(contract_name)
+            .is_some_and(|instances| instances.get(&label).is_some())
+    };
+    if exists(&*app_state.contracts.lock().await) {
+        return helper::ui_alert(&format!("instance {label} already exists"));
+    }
+    // the contracts stay unlocked while waiting for the deployment to be confirmed
+    let (contract, deployment) =
+        match lab::deploy_instance(&app_state, contract_name, &label, &args, value).await {
+            Ok(deployed) => deployed,
+            Err(e) => return helper::ui_alert(&e),
+        };
+    let mut lock = app_state.contracts.lock().await;
+    if exists(&lock) {
+        return helper::ui_alert(&format!("instance {label} already exists"));
+    }
+    lock.entry(contract_name.to_owned())
+        .or_default()
+        .insert(&label, contract);
+    drop(lock);
+    lab::record_deployment(&app_state, deployment).await;
+
+    HttpResponse::SeeOther()
+        .append_header(("Location", format!("/console/{contract_name}")))
+        .finish()
+}
+
+#[post("/console/{name}/call")]
+async fn call_handler(
+    name: web::Path<String>,
+    form: web::Form<FormFields>,
+    app_state: web::Data<AppState>,
+    req: HttpRequest,
+) -> impl Responder {
+    let contract_name = name.as_str();
+    let abi = match app_state.eth_client.contract_abi(contract_name) {
+        Ok(abi) => abi,
+        Err(e) => return helper::ui_alert(&e.to_string()),
+    };
+    let signature = form.get(SIGNATURE_FIELD).cloned().unwrap_or_default();
+    let function = match abi.functions().find(|f| f.signature() == signature) {
+        Some(function) => function,
+        None => return helper::ui_alert(&format!("function {signature} not found")),
+    };
+    let address = match helper::parse_address(form.get(ADDRESS_FIELD).map_or("", |a| a.trim())) {
+        Ok(address) => address,
+        Err(e) => return helper::ui_alert(&e.to_string()),
+    };
+    let args = match tokenize_args(&function.inputs, &form) {
+        Ok(args) => args,
+        Err(e) => return helper::ui_alert(&e),
+    };
+    let data = match function.encode_input(&args) {
+        Ok(data) => data,
+        Err(e) => return helper::ui_alert(&e.to_string()),
+    };
+    let value = match parse_value(&form) {
+        Ok(value) => value,
+        Err(e) => return helper::ui_alert(&e),
+    };
+
+    let client = match helper::get_signer_client(
+        &app_state.eth_client,
+        form.get(SIGNER_FIELD).map(|s| s.as_str()),
+    ) {
+        Ok(client) => client,
+        Err(e) => return helper::ui_alert(&e),
+    };
+    let impersonate =
+        match lab::parse_impersonation(&app_state, form.get(IMPERSONATE_FIELD).map(|i| i.as_str()))
+        {
+            Ok(impersonate) => impersonate,
+            Err(e) => return helper::ui_alert(&e),
+        };
+    let sender = impersonate.unwrap_or(client.address());
+
+    let tx = TransactionRequest::new()
+        .from(sender)
+        .to(address)
+        .data(data)
+        .value(value);
+    let mut context = Context::new();
+    context.insert("function_name", &function.name);
+
+    if is_read_only(function) {
+        let eth = client.clone();
+        let block_id = helper::get_block_id_from_header_value(req.headers());
+        let block_id = match helper::to_block_id(eth.clone(), block_id).await {
+            Ok(block_id) => block_id,
+            Err(e) => return helper::ui_alert(&e),
+        };
+        let tx = TypedTransaction::Legacy(tx);
+        let output = match eth.call(&tx, Some(block_id)).await {
+            Ok(output) => output,
+            Err(e) => {
+                return match e.as_error_response() {
+                    Some(response) if response.message.contains("revert") => {
+                        let revert = app_state
+                            .eth_client
+                            .call_revert(&tx, response.as_revert_data().as_ref())
+                            .await;
+                        helper::ui_alert(&revert.to_string())
+                    }
+                    _ => helper::ui_alert(&e.to_string()),
+                }
+            }
+        };
+        let outputs = match function.decode_output(&output) {
+            Ok(tokens) => function
+                .outputs
+                .iter()
+                .zip(tokens.iter())
+                .map(|(param, token)| (param_label(param), helper::format_token(token)))
+                .collect::<Vec<_>>(),
+            Err(e) => return helper::ui_alert(&e.to_string()),
+        };
+        context.insert("outputs", &outputs);
+
+        return match app_state.tmpl.render("console_result.html", &context) {
+            Ok(rendered) => HttpResponse::Ok().body(rendered),
+            Err(e) => helper::render_error(e),
+        };
+    }
+
+    app_state
+        .debug_service
+        .send_debug_event(&format!(
+            "<b>[{contract_name}]</b> transaction requested by {sender:#x}: {signature} {args:?}"
+        ))
+        .await;
+    let fee_overrides = match FeeOverrides::parse(
+        form.get(GAS_LIMIT_FIELD).map(|g| g.as_str()),
+        form.get(MAX_FEE_FIELD).map(|f| f.as_str()),
+        form.get(PRIORITY_FEE_FIELD).map(|f| f.as_str()),
+        form.get(GAS_PRICE_FIELD).map(|p| p.as_str()),
+    ) {
+        Ok(fee_overrides) => fee_overrides,
+        Err(e) => return helper::ui_alert(&e),
+    };
+    let confirmations = match lab::parse_confirmations(
+        &app_state,
+        form.get(CONFIRMATIONS_FIELD).map(|c| c.as_str()),
+    ) {
+        Ok(confirmations) => confirmations,
+        Err(e) => return helper::ui_alert(&e),
+    };
+    let label = format!("{contract_name}.{}", function.name);
+    let (tx_hash, fees) = match lab::send_transaction(
+        &app_state,
+        client,
+        tx.into(),
+        impersonate,
+        &fee_overrides,
+        &label,
+        confirmations,
+    )
+    .await
+    {
+        Ok(result) => result,
+        Err(e) => return helper::ui_alert(&e),
+    };
+
+    context.insert("transaction_hash", &format!("{tx_hash:#x}"));
+    context.insert("fees", &fees);
+
+    match app_state.tmpl.render("console_result.html", &context) {
+        Ok(rendered) => HttpResponse::Ok()
+            .append_header(("HX-Trigger", "loadBlockDetails, loadAccountBalances"))
+            .body(rendered),
+        Err(e) => helper::render_error(e),
+    }
+}
+
+fn tokenize_args(inputs: &[Param], form: &FormFields) -> Result<Vec<Token>, String> {
+    inputs
+        .iter()
+        .enumerate()
+        .map(|(i, param)| {
+            let value = form.get(&format!("arg{i}")).map_or("", |v| v.trim());
+            ConsoleTokenizer::tokenize(&param.kind, value)
+                .map_err(|e| format!("invalid value for {}: {e}", param_label(param)))
+        })
+        .collect()
+}
+
+/// Ether sent along with the call or deployment in wei, zero if not set.
+fn parse_value(form: &FormFields) -> Result<U256, String> {
+    match form.get(VALUE_FIELD).map(|v| v.trim()) {
+        Some(value) if !value.is_empty() => LenientTokenizer::tokenize_uint(value)
+            .map(U256::from)
+            .map_err(|e| format!("invalid value: {e}")),
+        _ => Ok(U256::zero()),
+    }
+}
+
+fn to_console_params(params: &[Param]) -> Vec<ConsoleParam> {
+    params
+        .iter()
+        .map(|p| ConsoleParam {
+            name: p.name.clone(),
+            kind: p.kind.to_string(),
+        })
+        .collect()
+}
+
+fn param_label(param: &Param) -> String {
+    if param.name.is_empty() {
+        param.kind.to_string()
+    } else {
+        format!("{} ({})", param.name, param.kind)
+    }
+}
+
+fn is_read_only(function: &Function) -> bool {
+    matches!(
+        function.state_mutability,
+        StateMutability::View | StateMutability::Pure
+    )
+}
@@ -0,0 +1,131 @@
+use crate::{client::Artifact, helper};
+use ethers::{
+    abi::{self, Param, RawLog},
+    types::{Bytes, Log},
+};
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct DecodedCall {
+    pub contract: String,
+    pub function: String,
+    pub params: Vec<(String, String)>,
+}
+
+#[derive(Serialize)]
+pub struct DecodedLog {
+    pub address: String,
+    pub contract: Option<String>,
+    pub event: Option<String>,
+    pub params: Vec<(String, String)>,
+    pub topics: Vec<String>,
+    pub data: String,
+}
+
+/// Decodes the calldata of a call by its selector or, for contract creations, the constructor
+/// args appended to the matching bytecode.
+pub fn decode_input(artifacts: &[Artifact], creation: bool, input: &Bytes) -> Option<DecodedCall> {
+    if creation {
+        return artifacts.iter().find_map(|(name, abi, bytecode)| {
+            if bytecode.is_empty() || !input.starts_with(bytecode) {
+                return None;
+            }
+            let inputs = abi
+                .constructor()
+                .map(|c| c.inputs.clone())
+                .unwrap_or_default();
+            let kinds: Vec<_> = inputs.iter().map(|p| p.kind.clone()).collect();
+            let tokens = abi::decode(&kinds, &input[bytecode.len()..]).ok()?;
+            Some(DecodedCall {
+                contract: name.clone(),
+                function: "constructor".to_owned(),
+                params: named_values(&inputs, &tokens),
+            })
+        });
+    }
+
+    if input.len() < 4 {
+        return None;
+    }
+    artifacts.iter().find_map(|(name, abi, _)| {
+        let function = abi
+            .functions()
+            .find(|f| f.short_signature() == input[..4])?;
+        let tokens = function.decode_input(&input[4..]).ok()?;
+        Some(DecodedCall {
+            contract: name.clone(),
+            function: function.signature(),
+            params: named_values(&function.inputs, &tokens),
+        })
+    })
+}
+
+/// Decodes the return data of a call by the selector of its calldata.
+pub fn decode_output(
+    artifacts: &[Artifact],
+    input: &Bytes,
+    output: &Bytes,
+) -> Option<Vec<(String, String)>> {
+    if input.len() < 4 {
+        return None;
+    }
+    artifacts.iter().find_map(|(_, abi, _)| {
+        let function = abi
+            .functions()
+            .find(|f| f.short_signature() == input[..4])?;
+        let tokens = function.decode_output(output).ok()?;
+        Some(named_values(&function.outputs, &tokens))
+    })
+}
+
+/// Decodes the log to the first compiled event with a matching signature and layout.
+pub fn decode_log(artifacts: &[Artifact], log: &Log) -> DecodedLog {
+    let mut decoded = DecodedLog {
+        address: format!("{:#x}", log.address),
+        contract: None,
+        event: None,
+        params: vec![],
+        topics: log.topics.iter().map(|t| format!("{:#x}", t)).collect(),
+        data: log.data.to_string(),
+    };
+    let topic0 = match log.topics.first() {
+        Some(topic0) => topic0,
+        None => return decoded,
+    };
+
+    for (name, abi, _) in artifacts {
+        for event in abi.events().filter(|e| e.signature() == *topic0) {
+            let raw_log = RawLog {
+                topics: log.topics.clone(),
+                data: log.data.to_vec(),
+            };
+            if let Ok(parsed) = event.parse_log(raw_log) {
+                decoded.contract = Some(name.clone());
+                decoded.event = Some(event.name.clone());
+                decoded.params = parsed
+                    .params
+                    .iter()
+                    .map(|p| (p.name.clone(), helper::format_token(&p.value)))
+                    .collect();
+                return decoded;
+            }
+        }
+    }
+    decoded
+}
+
+fn named_values(params: &[Param], tokens: &[abi::Token]) -> Vec<(String, String)> {
+    params
+        .iter()
+        .zip(tokens.iter())
+        .enumerate()
+        .map(|(i, (param, token))| {
+            let name = if param.name.is_empty() {
+                format!("arg{i} ({})", param.kind)
+            } else {
+                format!("{} ({})", param.name, param.kind)
+            };
+            (name, helper::format_token(token))
+        })
+        .collect()
+}
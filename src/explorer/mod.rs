@@ -0,0 +1,389 @@
+pub mod decoder;
+pub mod trace;
+
+use crate::{app::model::State as AppState, helper};
+use actix_web::{get, web, HttpResponse, Responder};
+use ethers::types::{Block, Transaction, TransactionReceipt, H256, U256};
+use ethers_providers::Middleware;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tera::Context;
+
+const EXPLORER_TEMPLATE: &str = "explorer.html";
+const BLOCKS_TEMPLATE: &str = "explorer_blocks.html";
+const BLOCK_TEMPLATE: &str = "explorer_block.html";
+const TX_TEMPLATE: &str = "explorer_tx.html";
+const TRACE_TEMPLATE: &str = "explorer_trace.html";
+const PAGE_SIZE: u64 = 15;
+
+#[derive(Deserialize, Debug)]
+struct ExplorerQuery {
+    tx: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PageQuery {
+    page: Option<u64>,
+}
+
+#[derive(Deserialize, Debug)]
+struct BlockQuery {
+    id: Option<String>,
+}
+
+#[derive(Serialize)]
+struct BlockSummary {
+    number: u64,
+    hash: String,
+    time: String,
+    transactions: usize,
+    gas_used: String,
+    miner: String,
+}
+
+#[derive(Serialize)]
+struct TransactionSummary {
+    hash: String,
+    from: String,
+    to: String,
+    value: String,
+    nonce: String,
+    gas: String,
+}
+
+pub fn setup_handlers(cfg: &mut web::ServiceConfig) {
+    cfg.service(explorer_handler)
+        .service(blocks_handler)
+        .service(block_handler)
+        .service(tx_handler)
+        .service(trace_handler);
+}
+
+#[get("/explorer")]
+async fn explorer_handler(
+    query: web::Query<ExplorerQuery>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let mut context = Context::new();
+    let content_url = match query.tx.as_deref() {
+        Some(tx) => format!("/explorer/tx/{tx}"),
+        None => "/explorer/blocks".to_owned(),
+    };
+    context.insert("content_url", &content_url);
+    match app_state.tmpl.render(EXPLORER_TEMPLATE, &context) {
+        Ok(rendered) => HttpResponse::Ok().body(rendered),
+        Err(e) => helper::render_error(e),
+    }
+}
+
+#[get("/explorer/blocks")]
+async fn blocks_handler(
+    query: web::Query<PageQuery>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let eth = app_state.eth_client.get_client();
+    let latest = match eth.get_block_number().await {
+        Ok(latest) => latest.as_u64(),
+        Err(e) => return helper::ui_alert(&e.to_string()),
+    };
+    let last_page = latest / PAGE_SIZE;
+    let page = query.page.unwrap_or(0).min(last_page);
+
+    let first = latest - page * PAGE_SIZE;
+    let last = first.saturating_sub(PAGE_SIZE - 1);
+    let mut blocks = vec![];
+    for number in (last..=first).rev() {
+        match eth.get_block(number).await {
+            Ok(Some(block)) => blocks.push(BlockSummary {
+                number,
+                hash: format!("{:#x}", block.hash.unwrap_or_default()),
+                time: block.time().map(|t| t.to_string()).unwrap_or_default(),
+                transactions: block.transactions.len(),
+                gas_used: block.gas_used.to_string(),
+                miner: format!("{:#x}", block.author.unwrap_or_default()),
+            }),
+            Ok(None) => continue,
+            Err(e) => return helper::ui_alert(&e.to_string()),
+        }
+    }
+
+    let mut context = Context::new();
+    context.insert("blocks", &blocks);
+    context.insert("page", &page);
+    context.insert("last_page", &last_page);
+    match app_state.tmpl.render(BLOCKS_TEMPLATE, &context) {
+        Ok(rendered) => HttpResponse::Ok().body(rendered),
+        Err(e) => helper::render_error(e),
+    }
+}
+
+#[get("/explorer/block")]
+async fn block_handler(
+    query: web::Query<BlockQuery>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let eth = app_state.eth_client.get_client();
+    let input = query.id.as_deref().map(|id| id.trim());
+    let latest = match eth.get_block_number().await {
+        Ok(latest) => latest.as_u64(),
+        Err(e) => return helper::ui_alert(&e.to_string()),
+    };
+
+    let mut context = Context::new();
+    context.insert("query", input.unwrap_or_default());
+    context.insert("latest", &latest);
+    let block_id = match helper::to_block_id(eth.clone(), input).await {
+        Ok(block_id) => block_id,
+        Err(e) => {
+            context.insert("error", &e);
+            return render_block(&app_state, &context);
+        }
+    };
+    match eth.get_block_with_txs(block_id).await {
+        Ok(Some(block)) => insert_block(&block, &mut context),
+        Ok(None) => context.insert(
+            "error",
+            &format!("block {} not found", input.unwrap_or("latest")),
+        ),
+        Err(e) => return helper::ui_alert(&e.to_string()),
+    }
+
+    render_block(&app_state, &context)
+}
+
+#[get("/explorer/tx/{hash}")]
+async fn tx_handler(hash: web::Path<String>, app_state: web::Data<AppState>) -> impl Responder {
+    let eth = app_state.eth_client.get_client();
+    let mut context = Context::new();
+    context.insert("hash", hash.as_str());
+
+    let tx_hash = match hash.trim().parse::<H256>() {
+        Ok(tx_hash) => tx_hash,
+        Err(e) => {
+            context.insert("error", &format!("invalid transaction hash: {e}"));
+            return render_tx(&app_state, &context);
+        }
+    };
+    let tx = match eth.get_transaction(tx_hash).await {
+        Ok(Some(tx)) => tx,
+        Ok(None) => {
+            context.insert("error", &format!("transaction {hash} not found"));
+            return render_tx(&app_state, &context);
+        }
+        Err(e) => return helper::ui_alert(&e.to_string()),
+    };
+    let receipt = match eth.get_transaction_receipt(tx_hash).await {
+        Ok(receipt) => receipt,
+        Err(e) => return helper::ui_alert(&e.to_string()),
+    };
+
+    let artifacts = app_state.eth_client.contract_artifacts();
+    insert_tx(&tx, receipt.as_ref(), &mut context);
+    context.insert(
+        "decoded_input",
+        &decoder::decode_input(&artifacts, tx.to.is_none(), &tx.input),
+    );
+    if let Some(receipt) = receipt.as_ref() {
+        let logs: Vec<_> = receipt
+            .logs
+            .iter()
+            .map(|log| decoder::decode_log(&artifacts, log))
+            .collect();
+        context.insert("logs", &logs);
+    }
+
+    render_tx(&app_state, &context)
+}
+
+#[get("/explorer/tx/{hash}/trace")]
+async fn trace_handler(hash: web::Path<String>, app_state: web::Data<AppState>) -> impl Responder {
+    let mut context = Context::new();
+    context.insert("hash", hash.as_str());
+
+    let tx_hash = match hash.trim().parse::<H256>() {
+        Ok(tx_hash) => tx_hash,
+        Err(e) => {
+            context.insert("error", &format!("invalid transaction hash: {e}"));
+            return render_trace(&app_state, &context);
+        }
+    };
+    let call_frame = match app_state.eth_client.trace_calls(tx_hash).await {
+        Ok(call_frame) => call_frame,
+        Err(e) => {
+            context.insert("error", &e.to_string());
+            return render_trace(&app_state, &context);
+        }
+    };
+    let step_frame = match app_state.eth_client.trace_steps(tx_hash).await {
+        Ok(step_frame) => step_frame,
+        Err(e) => {
+            context.insert("error", &e.to_string());
+            return render_trace(&app_state, &context);
+        }
+    };
+
+    let mut instances = HashMap::new();
+    for (name, contract_instances) in app_state.contracts.lock().await.iter() {
+        for (label, contract) in contract_instances.iter() {
+            instances.insert(contract.address(), format!("{name} ({label})"));
+        }
+    }
+    let artifacts = app_state.eth_client.contract_artifacts();
+    let (steps, revert_points) = trace::steps(&step_frame);
+
+    context.insert(
+        "calls",
+        &trace::flatten_calls(&call_frame, &artifacts, &instances),
+    );
+    context.insert("steps", &steps);
+    context.insert("revert_points", &revert_points);
+    context.insert("step_count", &step_frame.struct_logs.len());
+    context.insert("max_steps", &trace::MAX_STEPS);
+    context.insert("failed", &step_frame.failed);
+    context.insert("gas", &step_frame.gas);
+
+    render_trace(&app_state, &context)
+}
+
+fn render_trace(app_state: &AppState, context: &Context) -> HttpResponse {
+    match app_state.tmpl.render(TRACE_TEMPLATE, context) {
+        Ok(rendered) => HttpResponse::Ok().body(rendered),
+        Err(e) => helper::render_error(e),
+    }
+}
+
+fn insert_tx(tx: &Transaction, receipt: Option<&TransactionReceipt>, context: &mut Context) {
+    let mut fields = vec![
+        (
+            "Status",
+            match receipt.and_then(|r| r.status).map(|s| s.as_u64()) {
+                Some(1) => "success".to_owned(),
+                Some(_) => "failed".to_owned(),
+                None if receipt.is_none() => "pending".to_owned(),
+                None => "-".to_owned(),
+            },
+        ),
+        ("From", format!("{:#x}", tx.from)),
+        (
+            "To",
+            tx.to
+                .map(|to| format!("{:#x}", to))
+                .unwrap_or("contract creation".to_owned()),
+        ),
+        ("Value", tx.value.to_string()),
+        ("Nonce", tx.nonce.to_string()),
+        (
+            "Type",
+            tx.transaction_type
+                .map(|t| t.to_string())
+                .unwrap_or("0".to_owned()),
+        ),
+        ("Gas limit", tx.gas.to_string()),
+        ("Gas price", optional(tx.gas_price)),
+        ("Max fee per gas", optional(tx.max_fee_per_gas)),
+        (
+            "Max priority fee per gas",
+            optional(tx.max_priority_fee_per_gas),
+        ),
+    ];
+    if let Some(receipt) = receipt {
+        let gas_used = receipt.gas_used.unwrap_or_default();
+        fields.extend([
+            (
+                "Gas used",
+                format!(
+                    "{gas_used} of {} ({:.2}%)",
+                    tx.gas,
+                    gas_used.as_u128() as f64 * 100.0 / tx.gas.as_u128().max(1) as f64
+                ),
+            ),
+            ("Effective gas price", optional(receipt.effective_gas_price)),
+            (
+                "Created contract",
+                receipt
+                    .contract_address
+                    .map(|address| format!("{:#x}", address))
+                    .unwrap_or("-".to_owned()),
+            ),
+        ]);
+    }
+
+    context.insert(
+        "block_number",
+        &tx.block_number.map(|number| number.as_u64()),
+    );
+    context.insert("fields", &fields);
+    context.insert("input", &tx.input.to_string());
+}
+
+fn render_tx(app_state: &AppState, context: &Context) -> HttpResponse {
+    match app_state.tmpl.render(TX_TEMPLATE, context) {
+        Ok(rendered) => HttpResponse::Ok().body(rendered),
+        Err(e) => helper::render_error(e),
+    }
+}
+
+fn insert_block(block: &Block<Transaction>, context: &mut Context) {
+    let number = block.number.unwrap_or_default().as_u64();
+    let header = vec![
+        ("Number", number.to_string()),
+        ("Hash", format!("{:#x}", block.hash.unwrap_or_default())),
+        ("Parent hash", format!("{:#x}", block.parent_hash)),
+        (
+            "Time",
+            block.time().map(|t| t.to_string()).unwrap_or_default(),
+        ),
+        ("Timestamp", block.timestamp.to_string()),
+        ("Miner", format!("{:#x}", block.author.unwrap_or_default())),
+        ("Gas used", block.gas_used.to_string()),
+        ("Gas limit", block.gas_limit.to_string()),
+        ("Base fee per gas", optional(block.base_fee_per_gas)),
+        ("Size", optional(block.size)),
+        ("Nonce", format!("{:#x}", block.nonce.unwrap_or_default())),
+        ("Difficulty", block.difficulty.to_string()),
+        ("State root", format!("{:#x}", block.state_root)),
+        (
+            "Transactions root",
+            format!("{:#x}", block.transactions_root),
+        ),
+        ("Receipts root", format!("{:#x}", block.receipts_root)),
+        ("Extra data", block.extra_data.to_string()),
+        (
+            "Logs bloom",
+            block
+                .logs_bloom
+                .map(|bloom| format!("{:#x}", bloom))
+                .unwrap_or("-".to_owned()),
+        ),
+    ];
+    let transactions: Vec<TransactionSummary> = block
+        .transactions
+        .iter()
+        .map(|tx| TransactionSummary {
+            hash: format!("{:#x}", tx.hash),
+            from: format!("{:#x}", tx.from),
+            to: tx
+                .to
+                .map(|to| format!("{:#x}", to))
+                .unwrap_or("contract creation".to_owned()),
+            value: tx.value.to_string(),
+            nonce: tx.nonce.to_string(),
+            gas: tx.gas.to_string(),
+        })
+        .collect();
+
+    context.insert("number", &number);
+    context.insert("header", &header);
+    context.insert("transactions", &transactions);
+}
+
+fn render_block(app_state: &AppState, context: &Context) -> HttpResponse {
+    match app_state.tmpl.render(BLOCK_TEMPLATE, context) {
+        Ok(rendered) => HttpResponse::Ok().body(rendered),
+        Err(e) => helper::render_error(e),
+    }
+}
+
+fn optional(value: Option<U256>) -> String {
+    value.map(|v| v.to_string()).unwrap_or("-".to_owned())
+}
@@ -0,0 +1,123 @@
+use crate::{
+    client::{revert, Artifact},
+    explorer::decoder::{self, DecodedCall},
+};
+use ethers::types::{Address, CallFrame, DefaultFrame, NameOrAddress};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Maximum number of opcode steps rendered; revert points are always listed.
+pub const MAX_STEPS: usize = 500;
+
+/// Call of the call tree flattened with its nesting depth.
+#[derive(Serialize)]
+pub struct TraceCall {
+    pub depth: usize,
+    pub typ: String,
+    pub from: String,
+    pub to: String,
+    pub instance: Option<String>,
+    pub call: Option<DecodedCall>,
+    pub value: String,
+    pub gas: String,
+    pub gas_used: String,
+    pub input: String,
+    pub output: String,
+    pub decoded_output: Option<Vec<(String, String)>>,
+    pub error: Option<String>,
+    pub revert_reason: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct TraceStep {
+    pub index: usize,
+    pub pc: u64,
+    pub op: String,
+    pub depth: u64,
+    pub gas: u64,
+    pub gas_cost: u64,
+    pub error: Option<String>,
+}
+
+/// Flattens the call tree depth-first, resolving addresses to the known contract instances.
+pub fn flatten_calls(
+    frame: &CallFrame,
+    artifacts: &[Artifact],
+    instances: &HashMap<Address, String>,
+) -> Vec<TraceCall> {
+    let mut calls = vec![];
+    flatten_call(frame, 0, artifacts, instances, &mut calls);
+    calls
+}
+
+fn flatten_call(
+    frame: &CallFrame,
+    depth: usize,
+    artifacts: &[Artifact],
+    instances: &HashMap<Address, String>,
+    calls: &mut Vec<TraceCall>,
+) {
+    let to = match &frame.to {
+        Some(NameOrAddress::Address(address)) => Some(*address),
+        _ => None,
+    };
+    let creation = frame.typ.starts_with("CREATE");
+    let output = frame.output.clone().unwrap_or_default();
+    let reverted = frame.error.is_some();
+
+    calls.push(TraceCall {
+        depth,
+        typ: frame.typ.clone(),
+        from: format!("{:#x}", frame.from),
+        to: to.map(|to| format!("{:#x}", to)).unwrap_or("-".to_owned()),
+        instance: to.and_then(|to| instances.get(&to).cloned()),
+        call: decoder::decode_input(artifacts, creation, &frame.input),
+        value: frame.value.unwrap_or_default().to_string(),
+        gas: frame.gas.to_string(),
+        gas_used: frame.gas_used.to_string(),
+        input: frame.input.to_string(),
+        output: output.to_string(),
+        decoded_output: match reverted || creation {
+            true => None,
+            false => decoder::decode_output(artifacts, &frame.input, &output),
+        },
+        error: frame.error.clone(),
+        revert_reason: match reverted && !output.is_empty() {
+            true => Some(revert::decode(artifacts, &output).to_string()),
+            false => None,
+        },
+    });
+
+    for call in frame.calls.iter().flatten() {
+        flatten_call(call, depth + 1, artifacts, instances, calls);
+    }
+}
+
+/// Returns the first [`MAX_STEPS`] steps and all steps which reverted or failed.
+pub fn steps(frame: &DefaultFrame) -> (Vec<TraceStep>, Vec<TraceStep>) {
+    let to_step = |(index, log): (usize, &ethers::types::StructLog)| TraceStep {
+        index,
+        pc: log.pc,
+        op: log.op.clone(),
+        depth: log.depth,
+        gas: log.gas,
+        gas_cost: log.gas_cost,
+        error: log.error.clone(),
+    };
+
+    let first = frame
+        .struct_logs
+        .iter()
+        .enumerate()
+        .take(MAX_STEPS)
+        .map(to_step)
+        .collect();
+    let revert_points = frame
+        .struct_logs
+        .iter()
+        .enumerate()
+        .filter(|(_, log)| log.error.is_some() || log.op == "REVERT" || log.op == "INVALID")
+        .map(to_step)
+        .collect();
+    (first, revert_points)
+}
@@ -1,4 +1,4 @@
-use crate::{app::model::State as AppState, helper};
+use crate::{app::model::State as AppState, helper, lab};
 use actix_web::{get, post, web, Error, HttpRequest, HttpResponse, Responder};
 use ethers::types::Address;
 use ethers_providers::Middleware;
@@ -7,16 +7,18 @@ use tera::Context;
 pub fn setup_handlers(cfg: &mut web::ServiceConfig) {
     cfg.service(index)
         .service(deploy_handler)
-        .service(lab_handler)
         .service(load_block_details_handler)
         .service(block_details_handler)
         .service(account_balances_handler)
+        .service(connection_handler)
         .service(web::resource("/ws/debug").route(web::get().to(debug_events)));
 }
 
 #[get("/")]
 async fn index(app_state: web::Data<AppState>) -> impl Responder {
-    match app_state.tmpl.render("index.html", &Context::new()) {
+    let mut context = Context::new();
+    context.insert("labs", &lab::registry());
+    match app_state.tmpl.render("index.html", &context) {
         Ok(rendered) => HttpResponse::Ok().body(rendered),
         Err(e) => helper::render_error(e),
     }
@@ -32,22 +34,6 @@ async fn deploy_handler(name: web::Path<String>, app_state: web::Data<AppState>)
     }
 }
 
-#[get("/lab/{path:the-blockchain-messenger|smart-money|shared-wallet}/form")]
-async fn lab_handler(path: web::Path<String>, app_state: web::Data<AppState>) -> impl Responder {
-    let mut context = Context::new();
-    context.insert("current_address", &app_state.accounts[0]);
-    context.insert("other_account_addresses", &app_state.accounts[1..]);
-
-    let p = path.replace('-', "_");
-    let file_name = format!("lab/{p}/form.html");
-    match app_state.tmpl.render(&file_name, &context) {
-        Ok(rendered) => HttpResponse::Ok()
-            .append_header(("HX-Trigger", "loadResult"))
-            .body(rendered),
-        Err(e) => helper::render_error(e),
-    }
-}
-
 #[post("/load-block-details")]
 async fn load_block_details_handler() -> impl Responder {
     helper::trigger_reload()
@@ -62,8 +48,6 @@ async fn block_details_handler(app_state: web::Data<AppState>, req: HttpRequest)
         Err(e) => return helper::ui_alert(&e),
     };
 
-    let tx = block.transactions.get(0).unwrap();
-
     let mut context = Context::new();
     context.insert("block_number", &block.number.unwrap_or_default().as_u64());
     context.insert(
@@ -72,7 +56,15 @@ async fn block_details_handler(app_state: web::Data<AppState>, req: HttpRequest)
     );
     context.insert("parent_hash", &format!("{:#x}", block.parent_hash));
     context.insert("block_time", &block.time().unwrap_or_default().to_string());
-    context.insert("transaction", &format!("{:#x}", tx.hash));
+    context.insert("timestamp", &block.timestamp.as_u64());
+    context.insert(
+        "transactions",
+        &block
+            .transactions
+            .iter()
+            .map(|tx| format!("{:#x}", tx.hash))
+            .collect::<Vec<_>>(),
+    );
     context.insert("gas_used", &block.gas_used.as_u64());
 
     match app_state.tmpl.render("block_details.html", &context) {
@@ -111,6 +103,25 @@ async fn account_balances_handler(
     }
 }
 
+#[get("/connection")]
+async fn connection_handler(app_state: web::Data<AppState>) -> impl Responder {
+    // a request on a lost websocket starts the reconnect
+    let eth = app_state.eth_client.get_client();
+    let block_number = eth.get_block_number().await.ok().map(|n| n.as_u64());
+    let transport = app_state.eth_client.transport();
+
+    let mut context = Context::new();
+    context.insert("endpoint", app_state.eth_client.endpoint());
+    context.insert("transport", transport.name());
+    context.insert("connection", &transport.connection_state());
+    context.insert("block_number", &block_number);
+
+    match app_state.tmpl.render("connection_status.html", &context) {
+        Ok(rendered) => HttpResponse::Ok().body(rendered),
+        Err(e) => helper::render_error(e),
+    }
+}
+
 async fn debug_events(
     req: HttpRequest,
     stream: web::Payload,
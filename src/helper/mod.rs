@@ -1,39 +1,37 @@
 use crate::AppError::NoBlockFoundError;
-use crate::{client::ethereumclient::EthClient, AppError};
+use crate::{
+    client::{ethereumclient::EthClient, EthereumClient},
+    AppError,
+};
 use actix_web::{http::header::HeaderMap, HttpResponse};
 use ethers::{
-    abi::{decode as abi_decode, ParamType},
-    types::{Address, Block, BlockId, Bytes, Transaction, H256},
-    utils::{self, hex::decode as hex_decode},
+    abi::Token,
+    types::{Address, Block, BlockId, Bytes, Transaction, H256, I256},
+    utils,
 };
 use ethers_providers::Middleware;
 use std::env;
 
-const ACCOUNT: &str = "ACCOUNT";
-const OTHER_ACCOUNTS: &str = "OTHER_ACCOUNTS";
 const BLOCK_ID_HEADER: &str = "Blockid";
 
 pub fn get_env_var(key: &str) -> Result<String, AppError> {
     env::var(key).map_err(|e| AppError::KeyNotSetError(key.to_owned(), e))
 }
 
-pub fn get_all_account_addresses() -> Result<Vec<Address>, AppError> {
-    let current = get_env_var(ACCOUNT)?;
-    let other_addresses = get_env_var(OTHER_ACCOUNTS)?
-        .split_whitespace()
-        .map(|item| item.trim().to_lowercase())
-        .collect::<Vec<String>>();
-
-    let mut all_parsed = vec![Address::zero(); other_addresses.len() + 1];
-    all_parsed[0] = parse_address(&current)?;
-    for (i, addr) in other_addresses.iter().enumerate() {
-        all_parsed[i + 1] = match addr.parse::<Address>() {
-            Ok(addr) => addr,
-            Err(e) => return Err(AppError::AddressParseError(e.into())),
-        };
-    }
-
-    Ok(all_parsed)
+pub fn get_signer_client(
+    eth_client: &EthereumClient,
+    signer: Option<&str>,
+) -> Result<EthClient, String> {
+    let address = match signer.map(|s| s.trim()) {
+        Some(signer) if !signer.is_empty() => {
+            Some(parse_address(signer).map_err(|e| e.to_string())?)
+        }
+        _ => None,
+    };
+    eth_client.get_signer_client(address).ok_or(format!(
+        "no signer configured for {}",
+        signer.unwrap_or_default()
+    ))
 }
 
 pub fn ui_alert(msg: &str) -> HttpResponse {
@@ -57,19 +55,43 @@ pub fn trigger_reload() -> HttpResponse {
         .finish()
 }
 
+/// Link to the transaction detail page, e.g. for the debug feed.
+pub fn tx_link(tx_hash: &H256) -> String {
+    format!(
+        r##"<a class="link" hx-get="/explorer?tx={tx_hash:#x}" hx-target="#template">{tx_hash:#x}</a>"##
+    )
+}
+
 pub fn render_error<T: std::error::Error>(e: T) -> HttpResponse {
     let cause = e.source();
     ui_alert(cause.unwrap_or(&e).to_string().as_str())
 }
 
-pub fn decode_revert_error(e: &Bytes) -> String {
-    let decoded = hex_decode(&e.to_string()[10..])
-        .map_err(|e| e.to_string())
-        .unwrap();
-    let res = abi_decode(&[ParamType::String], decoded.as_slice())
-        .map_err(|e| e.to_string())
-        .unwrap();
-    format!("transaction reverted: {}", res[0])
+pub fn format_token(token: &Token) -> String {
+    match token {
+        Token::Address(adr) => format!("{adr:#x}"),
+        Token::Bytes(bytes) | Token::FixedBytes(bytes) => format!("{}", Bytes::from(bytes.clone())),
+        Token::Uint(nr) => nr.to_string(),
+        Token::Int(nr) => I256::from_raw(*nr).to_string(),
+        Token::Bool(b) => b.to_string(),
+        Token::String(s) => s.clone(),
+        Token::Array(tokens) | Token::FixedArray(tokens) => format!(
+            "[{}]",
+            tokens
+                .iter()
+                .map(format_token)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Token::Tuple(tokens) => format!(
+            "({})",
+            tokens
+                .iter()
+                .map(format_token)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
 }
 
 pub async fn to_block_id(eth: EthClient, input: Option<&str>) -> Result<BlockId, String> {
@@ -83,8 +105,12 @@ pub async fn to_block_id(eth: EthClient, input: Option<&str>) -> Result<BlockId,
                 let hash = H256::from_slice(&hex_decoded);
                 Ok(BlockId::from(hash))
             } else {
-                let nr = input.parse::<u64>().unwrap();
-                Ok(BlockId::from(nr))
+                match input.parse::<u64>() {
+                    Ok(nr) => Ok(BlockId::from(nr)),
+                    Err(_) => Err(format!(
+                        "{input} is neither a block number nor a block hash"
+                    )),
+                }
             }
         }
         _ => {
@@ -118,7 +144,7 @@ pub async fn get_block(eth: EthClient, input: Option<&str>) -> Result<Block<Tran
 pub fn get_block_id_from_header_value(headers: &HeaderMap) -> Option<&str> {
     match headers.get(BLOCK_ID_HEADER) {
         Some(block_id) => match block_id.to_str() {
-            Ok(block_id) if block_id.is_empty() => Some(block_id),
+            Ok(block_id) if !block_id.is_empty() => Some(block_id),
             _ => None,
         },
         None => None,
@@ -0,0 +1,56 @@
+//SPDX-License-Identifier: MIT
+
+pragma solidity ^0.8.15;
+
+import "./MinimalForwarder.sol";
+
+/// Messenger which accepts meta transactions relayed by its trusted forwarder (EIP-2771).
+contract MetaMessenger {
+
+    MinimalForwarder public trustedForwarder;
+
+    string public theMessage;
+
+    uint public changeCounter;
+
+    uint public relayedCounter;
+
+    // raw caller, the forwarder for relayed calls
+    address public lastMsgSender;
+
+    // caller resolved with `_msgSender()`, the signer of relayed calls
+    address public lastSender;
+
+    // account which sent the transaction and paid its gas
+    address public lastTxOrigin;
+
+    constructor() {
+        trustedForwarder = new MinimalForwarder();
+    }
+
+    function isTrustedForwarder(address forwarder) public view returns (bool) {
+        return forwarder == address(trustedForwarder);
+    }
+
+    function updateTheMessage(string calldata _newMessage) public {
+        theMessage = _newMessage;
+        changeCounter++;
+        if (isTrustedForwarder(msg.sender)) {
+            relayedCounter++;
+        }
+        lastMsgSender = msg.sender;
+        lastSender = _msgSender();
+        lastTxOrigin = tx.origin;
+    }
+
+    /// The trusted forwarder appends the signer of the request to the calldata.
+    function _msgSender() internal view returns (address sender) {
+        if (isTrustedForwarder(msg.sender) && msg.data.length >= 20) {
+            assembly {
+                sender := shr(96, calldataload(sub(calldatasize(), 20)))
+            }
+        } else {
+            sender = msg.sender;
+        }
+    }
+}
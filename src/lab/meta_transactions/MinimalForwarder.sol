@@ -0,0 +1,84 @@
+//SPDX-License-Identifier: MIT
+
+pragma solidity ^0.8.15;
+
+/// Forwards calls signed off-chain (EIP-712) by `from`, appending `from` to the calldata so that
+/// EIP-2771 recipients can recover the original sender.
+contract MinimalForwarder {
+
+    struct ForwardRequest {
+        address from;
+        address to;
+        uint256 value;
+        uint256 gas;
+        uint256 nonce;
+        bytes data;
+    }
+
+    bytes32 private constant DOMAIN_TYPEHASH =
+        keccak256("EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)");
+    bytes32 private constant REQUEST_TYPEHASH =
+        keccak256("ForwardRequest(address from,address to,uint256 value,uint256 gas,uint256 nonce,bytes data)");
+
+    mapping(address => uint256) private nonces;
+
+    event Executed(address indexed from, address indexed to, uint256 nonce, bytes signature, bool success);
+
+    function getNonce(address from) public view returns (uint256) {
+        return nonces[from];
+    }
+
+    function domainSeparator() public view returns (bytes32) {
+        return keccak256(abi.encode(
+            DOMAIN_TYPEHASH,
+            keccak256(bytes("MinimalForwarder")),
+            keccak256(bytes("0.0.1")),
+            block.chainid,
+            address(this)
+        ));
+    }
+
+    function verify(ForwardRequest calldata req, bytes calldata signature) public view returns (bool) {
+        bytes32 structHash = keccak256(abi.encode(
+            REQUEST_TYPEHASH,
+            req.from,
+            req.to,
+            req.value,
+            req.gas,
+            req.nonce,
+            keccak256(req.data)
+        ));
+        bytes32 digest = keccak256(abi.encodePacked("\x19\x01", domainSeparator(), structHash));
+        return nonces[req.from] == req.nonce && recover(digest, signature) == req.from;
+    }
+
+    function execute(ForwardRequest calldata req, bytes calldata signature) public payable returns (bool, bytes memory) {
+        require(verify(req, signature), "MinimalForwarder: signature does not match request");
+        nonces[req.from] = req.nonce + 1;
+
+        (bool success, bytes memory returndata) = req.to.call{gas: req.gas, value: req.value}(
+            abi.encodePacked(req.data, req.from)
+        );
+        // the relayer has to forward enough gas for the call (EIP-150 keeps 1/64 back)
+        if (gasleft() <= req.gas / 63) {
+            assembly {
+                invalid()
+            }
+        }
+
+        emit Executed(req.from, req.to, req.nonce, signature, success);
+        return (success, returndata);
+    }
+
+    function recover(bytes32 digest, bytes calldata signature) private pure returns (address) {
+        require(signature.length == 65, "MinimalForwarder: invalid signature length");
+        bytes32 r = bytes32(signature[0:32]);
+        bytes32 s = bytes32(signature[32:64]);
+        uint8 v = uint8(signature[64]);
+        require(
+            uint256(s) <= 0x7FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF5D576E7357A4501DDFE92F46681B20A0,
+            "MinimalForwarder: invalid signature s value"
+        );
+        return ecrecover(digest, v, r, s);
+    }
+}
@@ -0,0 +1,309 @@
+use crate::{
+    app::model::State as AppState,
+    client::{ethereumclient::EthClient, ContractInstanceType},
+    helper,
+    lab::{self, Lab},
+};
+use ethers::{
+    abi::AbiDecode,
+    contract::{abigen, EthCall},
+    types::{
+        transaction::{
+            eip2718::TypedTransaction,
+            eip712::{Eip712, TypedData},
+        },
+        Address, BlockId, BlockNumber, Bytes, NameOrAddress, H256, U256,
+    },
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tera::Context;
+
+// must match the EIP-712 domain of `MinimalForwarder.sol`
+const FORWARDER_NAME: &str = "MinimalForwarder";
+const FORWARDER_VERSION: &str = "0.0.1";
+const RELAYED_CALL_GAS: u64 = 300_000;
+
+#[derive(Deserialize, Debug)]
+pub enum Action {
+    Direct,
+    Relayed,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct FormData {
+    action: Action,
+    message: String,
+}
+
+/// Last request executed by the forwarder, read from its `Executed` events.
+#[derive(Serialize)]
+struct RelayedRequest {
+    from: String,
+    nonce: String,
+    signature: String,
+    success: bool,
+    transaction: String,
+}
+
+abigen!(
+    MetaMessenger,
+    r#"[
+        function updateTheMessage(string)
+        function theMessage()(string)
+        function changeCounter()(uint)
+        function relayedCounter()(uint)
+        function lastMsgSender()(address)
+        function lastSender()(address)
+        function lastTxOrigin()(address)
+        function trustedForwarder()(address)
+    ]"#
+);
+
+abigen!(
+    MinimalForwarder,
+    r#"[
+        struct ForwardRequest { address from; address to; uint256 value; uint256 gas; uint256 nonce; bytes data; }
+        event Executed(address indexed from, address indexed to, uint256 nonce, bytes signature, bool success)
+
+        function getNonce(address)(uint256)
+        function execute(ForwardRequest, bytes) payable returns (bool, bytes)
+    ]"#
+);
+
+pub struct MetaTransactionsLab;
+
+impl Lab for MetaTransactionsLab {
+    type Form = FormData;
+
+    const TITLE: &'static str = "Meta Transactions";
+    const CONTRACT_NAME: &'static str = "MetaMessenger";
+    const PATH: &'static str = "lab/meta_transactions";
+    const BASEURL: &'static str = "/lab/meta-transactions";
+
+    async fn read_state(
+        contract: &ContractInstanceType,
+        block_id: BlockId,
+        app_state: &AppState,
+        context: &mut Context,
+    ) -> Result<(), String> {
+        let contract = MetaMessenger::new(contract.address(), contract.client());
+
+        let message = match contract.the_message().block(block_id).call().await {
+            Ok(message) => message,
+            Err(e) => return Err(lab::call_error(app_state, e)),
+        };
+        context.insert("message", &message);
+
+        let counter = match contract.change_counter().block(block_id).call().await {
+            Ok(counter) => counter,
+            Err(e) => return Err(lab::call_error(app_state, e)),
+        };
+        context.insert("counter", &counter.as_u64());
+
+        let relayed_counter = match contract.relayed_counter().block(block_id).call().await {
+            Ok(counter) => counter,
+            Err(e) => return Err(lab::call_error(app_state, e)),
+        };
+        context.insert("relayed_counter", &relayed_counter.as_u64());
+
+        let senders = [
+            ("last_msg_sender", contract.last_msg_sender()),
+            ("last_sender", contract.last_sender()),
+            ("last_tx_origin", contract.last_tx_origin()),
+        ];
+        for (name, call) in senders {
+            match call.block(block_id).call().await {
+                Ok(address) => context.insert(name, &format!("{address:#x}")),
+                Err(e) => return Err(lab::call_error(app_state, e)),
+            }
+        }
+
+        let forwarder = match contract.trusted_forwarder().block(block_id).call().await {
+            Ok(forwarder) => MinimalForwarder::new(forwarder, contract.client()),
+            Err(e) => return Err(lab::call_error(app_state, e)),
+        };
+        context.insert("forwarder", &format!("{:#x}", forwarder.address()));
+        context.insert(
+            "relayer",
+            &format!("{:#x}", app_state.eth_client.relayer().address()),
+        );
+
+        let mut executed = forwarder.executed_filter().from_block(0u64);
+        if let BlockId::Number(number) = block_id {
+            executed = executed.to_block(number);
+        }
+        let last_relayed = match executed.query_with_meta().await {
+            Ok(events) => events.last().map(|(event, meta)| RelayedRequest {
+                from: format!("{:#x}", event.from),
+                nonce: event.nonce.to_string(),
+                signature: event.signature.to_string(),
+                success: event.success,
+                transaction: helper::tx_link(&meta.transaction_hash),
+            }),
+            Err(e) => return Err(e.to_string()),
+        };
+        context.insert("last_relayed", &last_relayed);
+
+        Ok(())
+    }
+
+    async fn transaction(
+        contract: &ContractInstanceType,
+        form: FormData,
+        app_state: &AppState,
+    ) -> Result<TypedTransaction, String> {
+        let client = contract.client();
+        let messenger = MetaMessenger::new(contract.address(), client.clone());
+        let call = messenger.update_the_message(form.message);
+        match form.action {
+            Action::Direct => Ok(call.tx),
+            Action::Relayed => {
+                let data = call.calldata().ok_or("could not encode the call")?;
+                relayed_transaction(app_state, client, messenger.address(), data).await
+            }
+        }
+    }
+
+    fn relayer(form: &FormData, app_state: &AppState) -> Option<EthClient> {
+        match form.action {
+            Action::Direct => None,
+            Action::Relayed => Some(app_state.eth_client.relayer()),
+        }
+    }
+
+    async fn transaction_sent(tx: &TypedTransaction, tx_hash: H256, app_state: &AppState) {
+        if let Err(e) = relayed_transaction_sent(tx, tx_hash, app_state).await {
+            log::error!("could not report the relayed transaction {tx_hash:#x}: {e}");
+        }
+    }
+}
+
+/// Signs a forward request for the call with the selected signer (EIP-712) and wraps it into the
+/// `execute` transaction which the relayer submits to the forwarder.
+async fn relayed_transaction(
+    app_state: &AppState,
+    client: EthClient,
+    to: Address,
+    data: Bytes,
+) -> Result<TypedTransaction, String> {
+    let messenger = MetaMessenger::new(to, client.clone());
+    let forwarder = match messenger.trusted_forwarder().call().await {
+        Ok(forwarder) => MinimalForwarder::new(forwarder, client.clone()),
+        Err(e) => return Err(lab::call_error(app_state, e)),
+    };
+    let from = client.address();
+    // counts the requests of the signer which are still pending
+    let nonce = match forwarder
+        .get_nonce(from)
+        .block(BlockNumber::Pending)
+        .call()
+        .await
+    {
+        Ok(nonce) => nonce,
+        Err(e) => return Err(lab::call_error(app_state, e)),
+    };
+    let request = ForwardRequest {
+        from,
+        to,
+        value: U256::zero(),
+        gas: U256::from(RELAYED_CALL_GAS),
+        nonce,
+        data,
+    };
+
+    let digest = typed_data(
+        &request,
+        forwarder.address(),
+        app_state.eth_client.chain_id(),
+    )?
+    .encode_eip712()
+    .map_err(|e| format!("could not encode the forward request: {e}"))?;
+    let signature = client
+        .signer()
+        .sign_hash(digest.into())
+        .map_err(|e| format!("could not sign the forward request: {e}"))?;
+
+    Ok(forwarder.execute(request, signature.to_vec().into()).tx)
+}
+
+/// Reports the forward request of a relayed transaction together with its typed data.
+async fn relayed_transaction_sent(
+    tx: &TypedTransaction,
+    tx_hash: H256,
+    app_state: &AppState,
+) -> Result<(), String> {
+    let call = match tx.data() {
+        Some(data) if data.starts_with(&ExecuteCall::selector()) => {
+            ExecuteCall::decode(data).map_err(|e| e.to_string())?
+        }
+        // sent directly to the messenger
+        _ => return Ok(()),
+    };
+    let forwarder = match tx.to() {
+        Some(NameOrAddress::Address(forwarder)) => *forwarder,
+        _ => return Err("relayed transaction has no forwarder address".to_owned()),
+    };
+    let request = call.0;
+    let typed_data = typed_data(&request, forwarder, app_state.eth_client.chain_id())?;
+    let digest = typed_data
+        .encode_eip712()
+        .map_err(|e| format!("could not encode the forward request: {e}"))?;
+    let typed_data_json = serde_json::to_string(&typed_data).map_err(|e| e.to_string())?;
+    app_state
+        .debug_service
+        .send_debug_event(&format!(
+            "<b>[MetaMessenger]</b> {:#x} signed forward request {} (digest {:#x}): {}, relayed by {:#x} in transaction {}<br/><code>{typed_data_json}</code>",
+            request.from,
+            request.nonce,
+            H256::from(digest),
+            call.1,
+            app_state.eth_client.relayer().address(),
+            helper::tx_link(&tx_hash)
+        ))
+        .await;
+    Ok(())
+}
+
+/// The forward request as EIP-712 typed data, which can be pasted into the signing tool to verify
+/// the signature.
+fn typed_data(
+    request: &ForwardRequest,
+    forwarder: Address,
+    chain_id: u64,
+) -> Result<TypedData, String> {
+    serde_json::from_value(json!({
+        "types": {
+            "EIP712Domain": [
+                {"name": "name", "type": "string"},
+                {"name": "version", "type": "string"},
+                {"name": "chainId", "type": "uint256"},
+                {"name": "verifyingContract", "type": "address"}
+            ],
+            "ForwardRequest": [
+                {"name": "from", "type": "address"},
+                {"name": "to", "type": "address"},
+                {"name": "value", "type": "uint256"},
+                {"name": "gas", "type": "uint256"},
+                {"name": "nonce", "type": "uint256"},
+                {"name": "data", "type": "bytes"}
+            ]
+        },
+        "primaryType": "ForwardRequest",
+        "domain": {
+            "name": FORWARDER_NAME,
+            "version": FORWARDER_VERSION,
+            "chainId": chain_id,
+            "verifyingContract": forwarder
+        },
+        "message": {
+            "from": request.from,
+            "to": request.to,
+            "value": request.value,
+            "gas": request.gas,
+            "nonce": request.nonce,
+            "data": request.data
+        }
+    }))
+    .map_err(|e| format!("invalid forward request: {e}"))
+}
@@ -1,29 +1,589 @@
+pub mod meta_transactions;
 pub mod shared_wallet;
 pub mod smart_money;
 pub mod the_blockchain_messenger;
 pub mod voting;
 
-pub use shared_wallet::main::setup_handlers as shared_wallet_handlers;
-pub use smart_money::main::setup_handlers as smart_money_handlers;
-pub use the_blockchain_messenger::main::setup_handlers as the_blockchain_messenger_handlers;
-pub use voting::main::setup_handlers as voting_handlers;
-
-use crate::{app::model::State as AppState, helper};
+use crate::{
+    app::{
+        deploymentregistry::Deployment,
+        model::{ContractInstances, State as AppState, DEFAULT_INSTANCE_LABEL},
+    },
+    client::{
+        ethereumclient::EthClient,
+        fees::{AppliedFees, FeeOverrides},
+        ContractInstanceType,
+    },
+    explorer::decoder::{self, DecodedCall},
+    helper,
+};
 use actix_web::{
     web::{self},
-    HttpResponse, Responder,
+    HttpRequest, HttpResponse, Responder,
+};
+use ethers::{
+    abi::Token,
+    types::{transaction::eip2718::TypedTransaction, Address, BlockId, BlockNumber, H256, U256},
+    utils::format_ether,
 };
-use ethers::abi::Tokenize;
-use std::path::Path;
+use ethers_contract::ContractError;
+use ethers_providers::{Middleware, MiddlewareError};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{collections::HashMap, fmt::Debug, future::Future, path::Path};
 use tera::Context;
 
+const LAB_TEMPLATE: &str = "lab_template.html";
+const INSTANCES_TEMPLATE: &str = "lab_instances.html";
+const COMPARE_TEMPLATE: &str = "lab_compare.html";
+const PREVIEW_TEMPLATE: &str = "lab_preview.html";
+
+#[derive(Deserialize, Debug)]
+struct NewInstanceForm {
+    label: String,
+    constructor_args: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct SignerSelection {
+    signer: Option<String>,
+    impersonate: Option<String>,
+    gas_limit: Option<String>,
+    max_fee_per_gas: Option<String>,
+    max_priority_fee_per_gas: Option<String>,
+    gas_price: Option<String>,
+    confirmations: Option<String>,
+    preview: Option<String>,
+}
+
+/// Predicted outcome of a transaction, shown before it is confirmed.
+#[derive(Serialize)]
+pub struct TransactionPreview {
+    from: String,
+    to: Option<String>,
+    value: String,
+    call: Option<DecodedCall>,
+    outputs: Vec<(String, String)>,
+    return_data: Option<String>,
+    revert: Option<String>,
+    gas_estimate: Option<String>,
+    max_cost: Option<String>,
+    estimate_error: Option<String>,
+    fees: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct ActiveInstanceForm {
+    label: String,
+}
+
+/// A lab is a contract under `src/lab/<name>` with a README, a form and a result template.
+/// Implementing this trait and adding the lab to [`registry`] mounts all of its routes.
+pub trait Lab {
+    /// Form data posted by the lab form, usually carrying an `action` discriminator.
+    type Form: DeserializeOwned + Debug + 'static;
+
+    /// Title shown in the sidebar menu.
+    const TITLE: &'static str;
+    /// Name of the compiled Solidity contract.
+    const CONTRACT_NAME: &'static str;
+    /// Template path of the lab directory (e.g. `lab/voting`).
+    const PATH: &'static str;
+    /// Base url of the lab routes (e.g. `/lab/voting`).
+    const BASEURL: &'static str;
+
+    /// Describes the constructor args input of new instances; `None` if there are no args.
+    const CONSTRUCTOR_ARGS_HINT: Option<&'static str> = None;
+
+    /// Builds the constructor args from the user input or from the lab defaults.
+    fn constructor_args(_input: Option<&str>) -> Result<Vec<Token>, String> {
+        Ok(vec![])
+    }
+
+    /// Adds lab specific variables to the form template context.
+    fn form_context(
+        _contract: &ContractInstanceType,
+        _block_id: BlockId,
+        _context: &mut Context,
+    ) -> impl Future<Output = Result<(), String>> {
+        async { Ok(()) }
+    }
+
+    /// Reads the contract state at the given block into the result template context.
+    fn read_state(
+        contract: &ContractInstanceType,
+        block_id: BlockId,
+        app_state: &AppState,
+        context: &mut Context,
+    ) -> impl Future<Output = Result<(), String>>;
+
+    /// Builds the transaction requested by the form; the contract is connected to the selected
+    /// signer, sending is left to the lab routes.
+    fn transaction(
+        contract: &ContractInstanceType,
+        form: Self::Form,
+        app_state: &AppState,
+    ) -> impl Future<Output = Result<TypedTransaction, String>>;
+
+    /// Client which sends the transaction instead of the selected signer, e.g. a relayer
+    /// submitting a request signed by the selected signer.
+    fn relayer(_form: &Self::Form, _app_state: &AppState) -> Option<EthClient> {
+        None
+    }
+
+    /// Called once the transaction was sent, not for previews.
+    fn transaction_sent(
+        _tx: &TypedTransaction,
+        _tx_hash: H256,
+        _app_state: &AppState,
+    ) -> impl Future<Output = ()> {
+        async {}
+    }
+}
+
+#[derive(Serialize)]
+pub struct LabEntry {
+    pub title: &'static str,
+    pub baseurl: &'static str,
+    #[serde(skip)]
+    configure: fn(&mut web::ServiceConfig),
+}
+
+impl LabEntry {
+    fn of<L: Lab + 'static>() -> Self {
+        LabEntry {
+            title: L::TITLE,
+            baseurl: L::BASEURL,
+            configure: configure_lab::<L>,
+        }
+    }
+}
+
+pub fn registry() -> Vec<LabEntry> {
+    vec![
+        LabEntry::of::<the_blockchain_messenger::main::TheBlockchainMessengerLab>(),
+        LabEntry::of::<smart_money::main::SmartMoneyLab>(),
+        LabEntry::of::<shared_wallet::main::SharedWalletLab>(),
+        LabEntry::of::<voting::main::VotingLab>(),
+        LabEntry::of::<meta_transactions::main::MetaTransactionsLab>(),
+    ]
+}
+
+pub fn setup_handlers(cfg: &mut web::ServiceConfig) {
+    for lab in registry() {
+        (lab.configure)(cfg);
+    }
+}
+
+fn configure_lab<L: Lab + 'static>(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource(L::BASEURL).route(web::get().to(load_template_handler::<L>)))
+        .service(
+            web::resource(format!("{}/result", L::BASEURL))
+                .route(web::get().to(tx_result_handler::<L>)),
+        )
+        .service(
+            web::resource(format!("{}/deploy", L::BASEURL))
+                .route(web::post().to(deploy_handler::<L>)),
+        )
+        .service(
+            web::resource(format!("{}/form", L::BASEURL))
+                .route(web::get().to(form_handler::<L>))
+                .route(web::post().to(submit_handler::<L>)),
+        )
+        .service(
+            web::resource(format!("{}/instances", L::BASEURL))
+                .route(web::get().to(instances_handler::<L>))
+                .route(web::post().to(new_instance_handler::<L>)),
+        )
+        .service(
+            web::resource(format!("{}/instances/active", L::BASEURL))
+                .route(web::post().to(active_instance_handler::<L>)),
+        )
+        .service(
+            web::resource(format!("{}/compare", L::BASEURL))
+                .route(web::get().to(compare_handler::<L>)),
+        );
+}
+
+async fn load_template_handler<L: Lab>(app_state: web::Data<AppState>) -> impl Responder {
+    load_template(app_state, L::PATH, L::CONTRACT_NAME, L::BASEURL).await
+}
+
+async fn form_handler<L: Lab>(app_state: web::Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let eth = app_state.eth_client.get_client();
+    let block_id = helper::get_block_id_from_header_value(req.headers());
+    let block_id = match helper::to_block_id(eth, block_id).await {
+        Ok(block_id) => block_id,
+        Err(e) => return helper::ui_alert(&e),
+    };
+
+    let mut context = Context::new();
+    context.insert("lab_baseurl", L::BASEURL);
+    context.insert("current_address", &app_state.accounts[0]);
+    context.insert("other_account_addresses", &app_state.accounts[1..]);
+
+    let lock = app_state.contracts.lock().await;
+    let contract = match lock.get(L::CONTRACT_NAME).and_then(|i| i.active()) {
+        Some(contract) => contract,
+        None => return helper::ui_alert(&format!("contract {} not deployed", L::CONTRACT_NAME)),
+    };
+    if let Err(e) = L::form_context(contract, block_id, &mut context).await {
+        return helper::ui_alert(&e);
+    }
+
+    let form_path = format!("{}/form.html", L::PATH);
+    match app_state.tmpl.render(&form_path, &context) {
+        Ok(rendered) => HttpResponse::Ok()
+            .append_header(("HX-Trigger", "loadResult, loadInstances"))
+            .body(rendered),
+        Err(e) => helper::render_error(e),
+    }
+}
+
+async fn tx_result_handler<L: Lab>(
+    app_state: web::Data<AppState>,
+    req: HttpRequest,
+) -> HttpResponse {
+    let eth = app_state.eth_client.get_client();
+    let block_id = helper::get_block_id_from_header_value(req.headers());
+    let block_id = match helper::to_block_id(eth, block_id).await {
+        Ok(block_id) => block_id,
+        Err(e) => return helper::ui_alert(&e),
+    };
+
+    let lock = app_state.contracts.lock().await;
+    let contract = match lock.get(L::CONTRACT_NAME).and_then(|i| i.active()) {
+        Some(contract) => contract,
+        None => return helper::ui_alert(&format!("contract {} not deployed", L::CONTRACT_NAME)),
+    };
+
+    let mut context = Context::new();
+    context.insert("contract_address", &format!("{:#x}", contract.address()));
+    if let Err(e) = L::read_state(contract, block_id, &app_state, &mut context).await {
+        return helper::ui_alert(&e);
+    }
+
+    let result_path = format!("{}/result.html", L::PATH);
+    match app_state.tmpl.render(&result_path, &context) {
+        Ok(rendered) => HttpResponse::Ok().body(rendered),
+        Err(e) => helper::render_error(e),
+    }
+}
+
+async fn deploy_handler<L: Lab>(app_state: web::Data<AppState>) -> HttpResponse {
+    let constructor_args = match L::constructor_args(None) {
+        Ok(args) => args,
+        Err(e) => return helper::ui_alert(&e),
+    };
+
+    deploy(
+        app_state,
+        L::CONTRACT_NAME,
+        L::BASEURL,
+        constructor_args.as_slice(),
+    )
+    .await
+}
+
+async fn submit_handler<L: Lab>(body: web::Bytes, app_state: web::Data<AppState>) -> HttpResponse {
+    let contract_name = L::CONTRACT_NAME;
+    let form = match serde_urlencoded::from_bytes::<L::Form>(&body) {
+        Ok(form) => form,
+        Err(e) => return helper::ui_alert(&e.to_string()),
+    };
+    let selection = match serde_urlencoded::from_bytes::<SignerSelection>(&body) {
+        Ok(selection) => selection,
+        Err(e) => return helper::ui_alert(&e.to_string()),
+    };
+    let client = match helper::get_signer_client(&app_state.eth_client, selection.signer.as_deref())
+    {
+        Ok(client) => client,
+        Err(e) => return helper::ui_alert(&e),
+    };
+    let impersonate = match parse_impersonation(&app_state, selection.impersonate.as_deref()) {
+        Ok(impersonate) => impersonate,
+        Err(e) => return helper::ui_alert(&e),
+    };
+    let fee_overrides = match FeeOverrides::parse(
+        selection.gas_limit.as_deref(),
+        selection.max_fee_per_gas.as_deref(),
+        selection.max_priority_fee_per_gas.as_deref(),
+        selection.gas_price.as_deref(),
+    ) {
+        Ok(fee_overrides) => fee_overrides,
+        Err(e) => return helper::ui_alert(&e),
+    };
+    let confirmations = match parse_confirmations(&app_state, selection.confirmations.as_deref()) {
+        Ok(confirmations) => confirmations,
+        Err(e) => return helper::ui_alert(&e),
+    };
+    let relayer = L::relayer(&form, &app_state);
+    if relayer.is_some() && impersonate.is_some() {
+        return helper::ui_alert(
+            "relayed requests are signed by the selected signer, impersonation is not supported",
+        );
+    }
+    let preview = selection.preview.as_deref() == Some("true");
+    let sender = impersonate.unwrap_or(client.address());
+    app_state
+        .debug_service
+        .send_debug_event(&format!(
+            "<b>[{contract_name}]</b> transaction {} by {sender:#x}: {form:?}",
+            if preview {
+                "preview requested"
+            } else {
+                "requested"
+            }
+        ))
+        .await;
+
+    let lock = app_state.contracts.lock().await;
+    let contract = match lock.get(contract_name).and_then(|i| i.active()) {
+        Some(contract) => contract.connect(client.clone()),
+        None => return helper::ui_alert(&format!("contract {} not deployed", contract_name)),
+    };
+    drop(lock);
+
+    let mut tx = match L::transaction(&contract, form, &app_state).await {
+        Ok(tx) => tx,
+        Err(e) => return helper::ui_alert(&e),
+    };
+    let client = match relayer {
+        Some(relayer) => {
+            tx.set_from(relayer.address());
+            relayer
+        }
+        None => client,
+    };
+    if preview {
+        return render_preview::<L>(&app_state, &body, client, tx, impersonate, &fee_overrides)
+            .await;
+    }
+    match send_transaction(
+        &app_state,
+        client,
+        tx.clone(),
+        impersonate,
+        &fee_overrides,
+        contract_name,
+        confirmations,
+    )
+    .await
+    {
+        Ok((tx_hash, _)) => {
+            L::transaction_sent(&tx, tx_hash, &app_state).await;
+            helper::trigger_reload()
+        }
+        Err(e) => helper::ui_alert(&e),
+    }
+}
+
+/// Renders the preview of the transaction with a form which resubmits the same fields to send it.
+async fn render_preview<L: Lab>(
+    app_state: &AppState,
+    body: &[u8],
+    client: EthClient,
+    tx: TypedTransaction,
+    impersonate: Option<Address>,
+    fee_overrides: &FeeOverrides,
+) -> HttpResponse {
+    let preview = match preview_transaction(app_state, client, tx, impersonate, fee_overrides).await
+    {
+        Ok(preview) => preview,
+        Err(e) => return helper::ui_alert(&e),
+    };
+    let fields = match serde_urlencoded::from_bytes::<Vec<(String, String)>>(body) {
+        Ok(fields) => fields
+            .into_iter()
+            .filter(|(name, _)| name != "preview")
+            .collect::<Vec<_>>(),
+        Err(e) => return helper::ui_alert(&e.to_string()),
+    };
+
+    let mut context = Context::new();
+    context.insert("lab_baseurl", L::BASEURL);
+    context.insert("preview", &preview);
+    context.insert("fields", &fields);
+    match app_state.tmpl.render(PREVIEW_TEMPLATE, &context) {
+        Ok(rendered) => HttpResponse::Ok()
+            .append_header(("HX-Retarget", "#txPreview"))
+            .body(rendered),
+        Err(e) => helper::render_error(e),
+    }
+}
+
+/// Describes the error of a contract call, decoding the revert data if the call reverted.
+pub fn call_error<M: Middleware>(app_state: &AppState, e: ContractError<M>) -> String {
+    match e.as_revert() {
+        Some(data) => app_state.eth_client.decode_revert(data).to_string(),
+        None => e.to_string(),
+    }
+}
+
+/// Parses the optional impersonated sender, which is only accepted if impersonation is enabled.
+pub fn parse_impersonation(
+    app_state: &AppState,
+    impersonate: Option<&str>,
+) -> Result<Option<Address>, String> {
+    match impersonate.map(|i| i.trim()) {
+        Some(address) if !address.is_empty() => {
+            if !app_state.eth_client.impersonation_enabled() {
+                return Err("impersonation is not enabled".to_owned());
+            }
+            helper::parse_address(address)
+                .map(Some)
+                .map_err(|e| e.to_string())
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Parses the number of confirmations to wait for, defaulting to the configured one.
+pub fn parse_confirmations(
+    app_state: &AppState,
+    confirmations: Option<&str>,
+) -> Result<u64, String> {
+    match confirmations.map(|c| c.trim()) {
+        Some(confirmations) if !confirmations.is_empty() => confirmations
+            .parse::<u64>()
+            .ok()
+            .filter(|c| *c > 0)
+            .ok_or(format!(
+                "invalid confirmations {confirmations}: expected at least 1"
+            )),
+        _ => Ok(app_state.eth_client.confirmations()),
+    }
+}
+
+/// Sends the transaction with the given client or, if set, as the impersonated address, and hands
+/// it to the tracker instead of waiting for it to be mined and confirmed. The fees which are not
+/// overridden are chosen by the fee strategy.
+pub async fn send_transaction(
+    app_state: &AppState,
+    client: EthClient,
+    mut tx: TypedTransaction,
+    impersonate: Option<Address>,
+    fee_overrides: &FeeOverrides,
+    label: &str,
+    confirmations: u64,
+) -> Result<(H256, AppliedFees), String> {
+    let fees = prepare_transaction(app_state, &client, &mut tx, impersonate, fee_overrides).await?;
+
+    let send = async {
+        match client.send_transaction(tx.clone(), None).await {
+            Ok(pending_tx) => Ok(pending_tx.tx_hash()),
+            Err(e) => Err(revert_error(app_state, &tx, e).await),
+        }
+    };
+    let result = match impersonate {
+        Some(sender) => app_state
+            .eth_client
+            .impersonated(sender, send)
+            .await
+            .map_err(|e| e.to_string())?,
+        None => send.await,
+    };
+
+    let tx_hash = result?;
+    app_state
+        .tx_tracker
+        .track(tx_hash, label, &fees.to_string(), confirmations)
+        .await;
+    Ok((tx_hash, fees))
+}
+
+/// Simulates the transaction against the pending block with `eth_call` and `eth_estimateGas`
+/// instead of sending it.
+pub async fn preview_transaction(
+    app_state: &AppState,
+    client: EthClient,
+    mut tx: TypedTransaction,
+    impersonate: Option<Address>,
+    fee_overrides: &FeeOverrides,
+) -> Result<TransactionPreview, String> {
+    let fees = prepare_transaction(app_state, &client, &mut tx, impersonate, fee_overrides).await?;
+    let artifacts = app_state.eth_client.contract_artifacts();
+    let input = tx.data().cloned().unwrap_or_default();
+    let mut preview = TransactionPreview {
+        from: format!("{:#x}", tx.from().copied().unwrap_or_default()),
+        to: tx.to_addr().map(|to| format!("{to:#x}")),
+        value: tx.value().copied().unwrap_or_default().to_string(),
+        call: decoder::decode_input(&artifacts, tx.to().is_none(), &input),
+        outputs: vec![],
+        return_data: None,
+        revert: None,
+        gas_estimate: None,
+        max_cost: None,
+        estimate_error: None,
+        fees: fees.to_string(),
+    };
+
+    let pending = Some(BlockNumber::Pending.into());
+    match client.call(&tx, pending).await {
+        Ok(output) => match decoder::decode_output(&artifacts, &input, &output) {
+            Some(outputs) => preview.outputs = outputs,
+            None if !output.is_empty() => preview.return_data = Some(output.to_string()),
+            None => {}
+        },
+        Err(e) => {
+            preview.revert = Some(revert_error(app_state, &tx, e).await);
+            return Ok(preview);
+        }
+    }
+    match client.estimate_gas(&tx, pending).await {
+        Ok(gas) => {
+            // the gas price of an EIP-1559 transaction is its max fee
+            let gas_price = tx.gas_price().unwrap_or_default();
+            preview.gas_estimate = Some(gas.to_string());
+            preview.max_cost = Some(format_ether(gas * gas_price));
+        }
+        Err(e) => preview.estimate_error = Some(e.to_string()),
+    }
+    Ok(preview)
+}
+
+/// Sets the sender and the fees of the transaction before it is sent or simulated.
+async fn prepare_transaction(
+    app_state: &AppState,
+    client: &EthClient,
+    tx: &mut TypedTransaction,
+    impersonate: Option<Address>,
+    fee_overrides: &FeeOverrides,
+) -> Result<AppliedFees, String> {
+    if let Some(sender) = impersonate {
+        tx.set_from(sender);
+    } else if tx.from().is_none() {
+        tx.set_from(client.address());
+    }
+    app_state
+        .eth_client
+        .apply_fees(tx, fee_overrides)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Describes an error of the node, decoding the revert reason if the transaction reverted.
+async fn revert_error<E: MiddlewareError>(
+    app_state: &AppState,
+    tx: &TypedTransaction,
+    e: E,
+) -> String {
+    match e.as_error_response() {
+        Some(response) if response.message.contains("revert") => app_state
+            .eth_client
+            .call_revert(tx, response.as_revert_data().as_ref())
+            .await
+            .to_string(),
+        _ => e.to_string(),
+    }
+}
+
 async fn load_template(
     app_state: web::Data<AppState>,
     lab_path: &str,
     contract_name: &str,
+    lab_baseurl: &str,
 ) -> impl Responder {
     let readme_path = format!("src/{}/README.md", lab_path);
-    let template_path = format!("{}/template.html", lab_path);
 
     let html = match markdown::file_to_html(Path::new(&readme_path)) {
         Ok(html) => html,
@@ -32,9 +592,20 @@ async fn load_template(
 
     let mut context = Context::new();
     context.insert("contract_name", contract_name);
+    context.insert("lab_baseurl", lab_baseurl);
     context.insert("readme", &html);
+    context.insert("signer_addresses", &app_state.eth_client.signer_addresses());
+    context.insert(
+        "impersonation_enabled",
+        &app_state.eth_client.impersonation_enabled(),
+    );
+    context.insert(
+        "fee_strategy",
+        &app_state.eth_client.fee_strategy().to_string(),
+    );
+    context.insert("confirmations", &app_state.eth_client.confirmations());
 
-    let rendered = match app_state.tmpl.render(&template_path, &context) {
+    let rendered = match app_state.tmpl.render(LAB_TEMPLATE, &context) {
         Ok(rendered) => rendered,
         Err(e) => return helper::render_error(e),
     };
@@ -42,68 +613,235 @@ async fn load_template(
     HttpResponse::Ok().body(rendered)
 }
 
-async fn deploy<T: Tokenize>(
+async fn instances_handler<L: Lab>(app_state: web::Data<AppState>) -> HttpResponse {
+    render_instances::<L>(&app_state, None).await
+}
+
+async fn new_instance_handler<L: Lab>(
+    form: web::Form<NewInstanceForm>,
+    app_state: web::Data<AppState>,
+) -> HttpResponse {
+    let label = form.label.trim();
+    if label.is_empty() {
+        return helper::ui_alert("instance label must be set");
+    }
+    let constructor_args = match L::constructor_args(form.constructor_args.as_deref()) {
+        Ok(args) => args,
+        Err(e) => return helper::ui_alert(&e),
+    };
+
+    let exists = |lock: &HashMap<String, ContractInstances>| {
+        lock.get(L::CONTRACT_NAME)
+            .is_some_and(|instances| instances.get(label).is_some())
+    };
+    if exists(&*app_state.contracts.lock().await) {
+        return helper::ui_alert(&format!("instance {label} already exists"));
+    }
+    // the contracts stay unlocked while waiting for the deployment to be confirmed
+    let (contract, deployment) = match deploy_instance(
+        &app_state,
+        L::CONTRACT_NAME,
+        label,
+        &constructor_args,
+        U256::zero(),
+    )
+    .await
+    {
+        Ok(deployed) => deployed,
+        Err(e) => return helper::ui_alert(&e),
+    };
+    let mut lock = app_state.contracts.lock().await;
+    if exists(&lock) {
+        return helper::ui_alert(&format!("instance {label} already exists"));
+    }
+    lock.entry(L::CONTRACT_NAME.to_owned())
+        .or_default()
+        .insert(label, contract);
+    drop(lock);
+    record_deployment(&app_state, deployment).await;
+
+    render_instances::<L>(&app_state, Some("loadForm")).await
+}
+
+async fn active_instance_handler<L: Lab>(
+    form: web::Form<ActiveInstanceForm>,
+    app_state: web::Data<AppState>,
+) -> HttpResponse {
+    let mut lock = app_state.contracts.lock().await;
+    let activated = lock
+        .get_mut(L::CONTRACT_NAME)
+        .map(|i| i.set_active(&form.label))
+        .unwrap_or(false);
+    drop(lock);
+    if !activated {
+        return helper::ui_alert(&format!("instance {} not found", form.label));
+    }
+
+    render_instances::<L>(&app_state, Some("loadForm")).await
+}
+
+async fn render_instances<L: Lab>(app_state: &AppState, trigger: Option<&str>) -> HttpResponse {
+    let lock = app_state.contracts.lock().await;
+    let (labels, active_label) = match lock.get(L::CONTRACT_NAME) {
+        Some(instances) => (
+            instances
+                .iter()
+                .map(|(label, contract)| (label.clone(), format!("{:#x}", contract.address())))
+                .collect::<Vec<_>>(),
+            instances.active_label().unwrap_or_default().to_owned(),
+        ),
+        None => (vec![], String::new()),
+    };
+    drop(lock);
+
+    let mut context = Context::new();
+    context.insert("lab_baseurl", L::BASEURL);
+    context.insert("instances", &labels);
+    context.insert("active_label", &active_label);
+    context.insert("constructor_args_hint", &L::CONSTRUCTOR_ARGS_HINT);
+
+    match app_state.tmpl.render(INSTANCES_TEMPLATE, &context) {
+        Ok(rendered) => {
+            let mut response = HttpResponse::Ok();
+            if let Some(trigger) = trigger {
+                response.append_header(("HX-Trigger", trigger));
+            }
+            response.body(rendered)
+        }
+        Err(e) => helper::render_error(e),
+    }
+}
+
+async fn compare_handler<L: Lab>(app_state: web::Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let eth = app_state.eth_client.get_client();
+    let block_id = helper::get_block_id_from_header_value(req.headers());
+    let block_id = match helper::to_block_id(eth, block_id).await {
+        Ok(block_id) => block_id,
+        Err(e) => return helper::ui_alert(&e),
+    };
+
+    let lock = app_state.contracts.lock().await;
+    let instances = match lock.get(L::CONTRACT_NAME) {
+        Some(instances) => instances,
+        None => return helper::ui_alert(&format!("contract {} not deployed", L::CONTRACT_NAME)),
+    };
+
+    let result_path = format!("{}/result.html", L::PATH);
+    let mut results = vec![];
+    for (label, contract) in instances.iter() {
+        let mut context = Context::new();
+        context.insert("contract_address", &format!("{:#x}", contract.address()));
+        if let Err(e) = L::read_state(contract, block_id, &app_state, &mut context).await {
+            return helper::ui_alert(&format!("{label}: {e}"));
+        }
+        match app_state.tmpl.render(&result_path, &context) {
+            Ok(rendered) => results.push((label.clone(), rendered)),
+            Err(e) => return helper::render_error(e),
+        }
+    }
+
+    let mut context = Context::new();
+    context.insert("results", &results);
+    match app_state.tmpl.render(COMPARE_TEMPLATE, &context) {
+        Ok(rendered) => HttpResponse::Ok().body(rendered),
+        Err(e) => helper::render_error(e),
+    }
+}
+
+async fn deploy(
     app_state: web::Data<AppState>,
     contract_name: &str,
-    contract_address_envvar: &str,
     lab_baseurl: &str,
-    constructor_args: T,
+    constructor_args: &[Token],
 ) -> HttpResponse {
-    let mut lock = app_state.contracts.lock().await;
+    let lock = app_state.contracts.lock().await;
+    let deployed = lock
+        .get(contract_name)
+        .and_then(|instances| instances.active())
+        .map(|contract| contract.address());
+    drop(lock);
 
-    if let Some(contract) = lock.get(contract_name) {
-        println!(
-            "contract {} already deployed: {:?}",
-            contract_name, contract
-        );
+    if let Some(address) = deployed {
+        log::debug!("contract {contract_name} already deployed at {address:#x}");
     } else {
-        let contract = match helper::get_env_var(contract_address_envvar) {
-            Ok(adr) => {
-                app_state
-                    .debug_service
-                    .send_debug_event(&format!(
-                        "<b>[{contract_name}]</b> recreating contract {contract_name}.sol from address {adr}"))
-                    .await;
-                match app_state
-                    .eth_client
-                    .contract_from_address(contract_name, adr.as_str())
-                    .await
-                {
-                    Ok(contract) => contract,
-                    Err(e) => return helper::ui_alert(&e.to_string()),
-                }
-            }
-            Err(_) => {
-                app_state
-                    .debug_service
-                    .send_debug_event(&format!(
-                        "<b>[{contract_name}]</b> deploying contract {contract_name}.sol ..."
-                    ))
-                    .await;
-
-                match app_state
-                    .eth_client
-                    .deploy_contract(contract_name, constructor_args)
-                    .await
-                {
-                    Ok(contract) => {
-                        let adr = contract.address();
-                        app_state
-                            .debug_service
-                            .send_debug_event(&format!(
-                                "<b>[{contract_name}]</b> {contract_name}.sol deployed to address {adr:#x}"))
-                            .await;
-                        contract
-                    }
-                    Err(e) => return helper::ui_alert(&e.to_string()),
-                }
-            }
+        // the contracts stay unlocked while waiting for the deployment to be confirmed
+        let (contract, deployment) = match deploy_instance(
+            &app_state,
+            contract_name,
+            DEFAULT_INSTANCE_LABEL,
+            constructor_args,
+            U256::zero(),
+        )
+        .await
+        {
+            Ok(deployed) => deployed,
+            Err(e) => return helper::ui_alert(&e),
         };
-
-        lock.insert(contract_name.to_owned(), contract);
+        let mut lock = app_state.contracts.lock().await;
+        let instances = lock.entry(contract_name.to_owned()).or_default();
+        // another request may have deployed an instance in the meantime
+        if instances.active().is_none() {
+            instances.insert(DEFAULT_INSTANCE_LABEL, contract);
+            drop(lock);
+            record_deployment(&app_state, deployment).await;
+        }
     }
 
     HttpResponse::SeeOther()
         .append_header(("Location", lab_baseurl.to_owned() + "/form"))
         .finish()
 }
+
+/// Deploys the contract and returns the deployment to record once the instance is kept under its
+/// label, so that a deployment which lost a race for the label is never reattached.
+pub async fn deploy_instance(
+    app_state: &AppState,
+    contract_name: &str,
+    label: &str,
+    constructor_args: &[Token],
+    value: U256,
+) -> Result<(ContractInstanceType, Deployment), String> {
+    app_state
+        .debug_service
+        .send_debug_event(&format!(
+            "<b>[{contract_name}]</b> deploying contract {contract_name}.sol ({label}) ..."
+        ))
+        .await;
+
+    let bytecode_hash = app_state
+        .eth_client
+        .bytecode_hash(contract_name)
+        .map_err(|e| e.to_string())?;
+    let (contract, receipt) = app_state
+        .eth_client
+        .deploy_contract(contract_name, constructor_args, value)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let adr = contract.address();
+    app_state
+        .debug_service
+        .send_debug_event(&format!(
+            "<b>[{contract_name}]</b> {contract_name}.sol ({label}) deployed to address {adr:#x} in transaction {}",
+            helper::tx_link(&receipt.transaction_hash)
+        ))
+        .await;
+
+    let deployment = Deployment::new(
+        &app_state.eth_client,
+        contract_name,
+        label,
+        bytecode_hash,
+        &receipt,
+        constructor_args.iter().map(helper::format_token).collect(),
+    );
+
+    Ok((contract, deployment))
+}
+
+pub async fn record_deployment(app_state: &AppState, deployment: Deployment) {
+    let contract_name = deployment.contract_name.clone();
+    if let Err(e) = app_state.deployments.record(deployment).await {
+        log::error!("could not record deployment of {contract_name}: {e}");
+    }
+}
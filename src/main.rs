@@ -1,23 +1,34 @@
 mod app;
 mod client;
+mod console;
+mod explorer;
 mod handlers;
 mod helper;
 mod lab;
+mod mining;
+mod rawtx;
+mod signing;
+mod snapshot;
+mod transactions;
 
 use client::EthereumClient;
-use futures::executor::block_on;
 use futures::lock::Mutex;
 use lab::voting;
 
-use std::{collections::HashMap, sync::Arc, thread};
+use std::sync::Arc;
 
 use actix_files as fs;
 use actix_web::{middleware::Logger, web, App, HttpServer};
 pub use app::{
-    debugservice::DebugService as AppDebug, model::Error as AppError, model::State as AppState,
+    debugservice::DebugService as AppDebug, deploymentregistry::DeploymentRegistry,
+    model::Error as AppError, model::State as AppState, txtracker::TxTracker,
 };
 use tera::Tera;
 
+const KEYSTORE_COMMAND: &str = "keystore";
+const DEPLOYMENTS_FILE: &str = "DEPLOYMENTS_FILE";
+const DEFAULT_DEPLOYMENTS_FILE: &str = "deployments.json";
+
 fn create_tera() -> Result<Tera, tera::Error> {
     let mut tera = Tera::new("templates/*.html")?;
     let labs_tera = Tera::new("src/**/*.html")?;
@@ -30,28 +41,75 @@ fn create_tera() -> Result<Tera, tera::Error> {
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(|a| a.as_str()) == Some(KEYSTORE_COMMAND) {
+        if let Err(e) = client::keystore::run_command(&args[1..]) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("debug"));
 
+    // kept until the server stops, dropping it shuts the spawned node down
+    let _anvil = match client::anvilnode::spawn_from_env() {
+        Ok(anvil) => anvil,
+        Err(e) => {
+            log::error!("{e}");
+            std::process::exit(1);
+        }
+    };
     let eth_client = EthereumClient::new().await.unwrap();
     let client_copy = eth_client.clone().get_client();
-    thread::spawn(move || block_on(voting::main::subscribe_to_events(client_copy)));
+    actix_web::rt::spawn(voting::main::subscribe_to_events(client_copy));
     let debug_svc = AppDebug::new();
-    let contracts_map = Arc::new(Mutex::new(HashMap::new()));
+
+    let deployments_file =
+        helper::get_env_var(DEPLOYMENTS_FILE).unwrap_or(DEFAULT_DEPLOYMENTS_FILE.to_owned());
+    let deployment_registry = DeploymentRegistry::load(&deployments_file).unwrap();
+    for deployment in deployment_registry.prune(&eth_client).await.unwrap() {
+        log::info!(
+            "pruned stale deployment of {} at {:#x} (chain id {})",
+            deployment.contract_name,
+            deployment.address,
+            deployment.chain_id
+        );
+    }
+    let reattached = deployment_registry.reattach(&eth_client).await.unwrap();
+    for (name, instances) in reattached.iter() {
+        for (label, contract) in instances.iter() {
+            log::info!("reattached {name} ({label}) at {:#x}", contract.address());
+        }
+    }
+    let contracts_map = Arc::new(Mutex::new(reattached));
+    let snapshots = Arc::new(Mutex::new(vec![]));
+    let tx_tracker = TxTracker::new(
+        eth_client.clone(),
+        debug_svc.clone(),
+        create_tera().unwrap(),
+    );
 
     HttpServer::new(move || {
         let logger = Logger::default();
 
         let tera = create_tera().unwrap();
         let eth_client = eth_client.clone();
-        let addresses = helper::get_all_account_addresses().unwrap();
+        let addresses = eth_client.signer_addresses();
         let debug_service = debug_svc.clone();
         let contracts = contracts_map.clone();
+        let deployments = deployment_registry.clone();
+        let snapshots = snapshots.clone();
+        let tx_tracker = tx_tracker.clone();
 
         let state = AppState {
             tmpl: tera,
             eth_client,
             contracts,
             debug_service,
+            deployments,
+            snapshots,
+            tx_tracker,
             accounts: addresses,
         };
         App::new()
@@ -59,10 +117,14 @@ async fn main() -> std::io::Result<()> {
             .app_data(web::Data::new(state))
             .service(fs::Files::new("static", "templates/static"))
             .configure(handlers::setup_handlers)
-            .configure(lab::the_blockchain_messenger_handlers)
-            .configure(lab::smart_money_handlers)
-            .configure(lab::shared_wallet_handlers)
-            .configure(lab::voting_handlers)
+            .configure(lab::setup_handlers)
+            .configure(console::setup_handlers)
+            .configure(explorer::setup_handlers)
+            .configure(snapshot::setup_handlers)
+            .configure(mining::setup_handlers)
+            .configure(transactions::setup_handlers)
+            .configure(rawtx::setup_handlers)
+            .configure(signing::setup_handlers)
     })
     .bind(("0.0.0.0", 8080))?
     .run()
@@ -0,0 +1,216 @@
+use crate::{app::model::State as AppState, helper, snapshot};
+use actix_web::{get, post, web, HttpResponse, Responder};
+use ethers::types::BlockNumber;
+use ethers_providers::Middleware;
+use serde::Deserialize;
+use tera::Context;
+
+const MINING_TEMPLATE: &str = "mining.html";
+const MINING_STATUS_TEMPLATE: &str = "mining_status.html";
+const MAX_BLOCKS_PER_REQUEST: u64 = 1000;
+
+#[derive(Deserialize, Debug)]
+struct MineForm {
+    blocks: Option<u64>,
+}
+
+#[derive(Deserialize, Debug)]
+struct SecondsForm {
+    seconds: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct TimestampForm {
+    timestamp: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct ReorgForm {
+    depth: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct AutomineForm {
+    enabled: bool,
+}
+
+pub fn setup_handlers(cfg: &mut web::ServiceConfig) {
+    cfg.service(mining_handler)
+        .service(mining_status_handler)
+        .service(mine_handler)
+        .service(increase_time_handler)
+        .service(next_timestamp_handler)
+        .service(automine_handler)
+        .service(interval_mining_handler)
+        .service(reorg_handler);
+}
+
+#[get("/mining")]
+async fn mining_handler(app_state: web::Data<AppState>) -> impl Responder {
+    match app_state.tmpl.render(MINING_TEMPLATE, &Context::new()) {
+        Ok(rendered) => HttpResponse::Ok().body(rendered),
+        Err(e) => helper::render_error(e),
+    }
+}
+
+#[get("/mining/status")]
+async fn mining_status_handler(app_state: web::Data<AppState>) -> impl Responder {
+    render_status(&app_state, None).await
+}
+
+#[post("/mining/mine")]
+async fn mine_handler(form: web::Form<MineForm>, app_state: web::Data<AppState>) -> impl Responder {
+    let blocks = form.blocks.unwrap_or(1);
+    if !(1..=MAX_BLOCKS_PER_REQUEST).contains(&blocks) {
+        return helper::ui_alert(&format!(
+            "number of blocks must be between 1 and {MAX_BLOCKS_PER_REQUEST}"
+        ));
+    }
+    for _ in 0..blocks {
+        if let Err(e) = app_state.eth_client.mine().await {
+            return helper::ui_alert(&e.to_string());
+        }
+    }
+    app_state
+        .debug_service
+        .send_debug_event(&format!("<b>[Mining]</b> mined {blocks} block(s)"))
+        .await;
+
+    render_status(&app_state, Some("loadBlockDetails, loadAccountBalances")).await
+}
+
+#[post("/mining/increase-time")]
+async fn increase_time_handler(
+    form: web::Form<SecondsForm>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let offset = match app_state.eth_client.increase_time(form.seconds).await {
+        Ok(offset) => offset,
+        Err(e) => return helper::ui_alert(&e.to_string()),
+    };
+    app_state
+        .debug_service
+        .send_debug_event(&format!(
+            "<b>[Mining]</b> increased time by {}s (total offset {offset}s)",
+            form.seconds
+        ))
+        .await;
+
+    render_status(&app_state, None).await
+}
+
+#[post("/mining/next-timestamp")]
+async fn next_timestamp_handler(
+    form: web::Form<TimestampForm>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = app_state
+        .eth_client
+        .set_next_block_timestamp(form.timestamp)
+        .await
+    {
+        return helper::ui_alert(&e.to_string());
+    }
+    app_state
+        .debug_service
+        .send_debug_event(&format!(
+            "<b>[Mining]</b> next block timestamp set to {}",
+            form.timestamp
+        ))
+        .await;
+
+    render_status(&app_state, None).await
+}
+
+#[post("/mining/automine")]
+async fn automine_handler(
+    form: web::Form<AutomineForm>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = app_state.eth_client.set_automine(form.enabled).await {
+        return helper::ui_alert(&e.to_string());
+    }
+    app_state
+        .debug_service
+        .send_debug_event(&format!("<b>[Mining]</b> automine set to {}", form.enabled))
+        .await;
+
+    render_status(&app_state, None).await
+}
+
+#[post("/mining/interval")]
+async fn interval_mining_handler(
+    form: web::Form<SecondsForm>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = app_state.eth_client.set_interval_mining(form.seconds).await {
+        return helper::ui_alert(&e.to_string());
+    }
+    app_state
+        .debug_service
+        .send_debug_event(&format!(
+            "<b>[Mining]</b> interval mining set to {}s",
+            form.seconds
+        ))
+        .await;
+
+    render_status(&app_state, None).await
+}
+
+#[post("/mining/reorg")]
+async fn reorg_handler(
+    form: web::Form<ReorgForm>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    if !(1..=MAX_BLOCKS_PER_REQUEST).contains(&form.depth) {
+        return helper::ui_alert(&format!(
+            "reorg depth must be between 1 and {MAX_BLOCKS_PER_REQUEST}"
+        ));
+    }
+    if let Err(e) = app_state.eth_client.reorg(form.depth).await {
+        return helper::ui_alert(&e.to_string());
+    }
+    app_state
+        .debug_service
+        .send_debug_event(&format!(
+            "<b>[Mining]</b> reorged the last {} block(s)",
+            form.depth
+        ))
+        .await;
+
+    app_state.tx_tracker.recheck().await;
+    if let Err(e) = snapshot::invalidate_contracts(&app_state).await {
+        return helper::ui_alert(&e);
+    }
+    render_status(&app_state, Some("loadBlockDetails, loadAccountBalances")).await
+}
+
+async fn render_status(app_state: &AppState, trigger: Option<&str>) -> HttpResponse {
+    let eth = app_state.eth_client.get_client();
+    let automine = match app_state.eth_client.automine().await {
+        Ok(automine) => automine,
+        Err(e) => return helper::ui_alert(&e.to_string()),
+    };
+    let block = match eth.get_block(BlockNumber::Latest).await {
+        Ok(Some(block)) => block,
+        Ok(None) => return helper::ui_alert("no latest block found"),
+        Err(e) => return helper::ui_alert(&e.to_string()),
+    };
+
+    let mut context = Context::new();
+    context.insert("automine", &automine);
+    context.insert("block_number", &block.number.unwrap_or_default().as_u64());
+    context.insert("timestamp", &block.timestamp.as_u64());
+    context.insert("block_time", &block.time().unwrap_or_default().to_string());
+
+    match app_state.tmpl.render(MINING_STATUS_TEMPLATE, &context) {
+        Ok(rendered) => {
+            let mut response = HttpResponse::Ok();
+            if let Some(trigger) = trigger {
+                response.append_header(("HX-Trigger", trigger));
+            }
+            response.body(rendered)
+        }
+        Err(e) => helper::render_error(e),
+    }
+}
@@ -0,0 +1,393 @@
+use crate::{
+    app::model::State as AppState,
+    client::fees::{self, AppliedFees},
+    helper,
+};
+use actix_web::{get, post, web, HttpResponse, Responder};
+use ethers::{
+    abi::token::{LenientTokenizer, Tokenizer},
+    types::{
+        transaction::{eip2718::TypedTransaction, eip2930::AccessList},
+        Address, BlockNumber, Bytes, Eip1559TransactionRequest, Eip2930TransactionRequest,
+        NameOrAddress, TransactionRequest, U256,
+    },
+    utils::rlp,
+};
+use ethers_providers::Middleware;
+use serde::{Deserialize, Serialize};
+use tera::Context;
+
+const RAWTX_TEMPLATE: &str = "rawtx.html";
+const RAWTX_FORM_TEMPLATE: &str = "rawtx_form.html";
+const RAWTX_RESULT_TEMPLATE: &str = "rawtx_result.html";
+const TRACKER_LABEL: &str = "raw transaction";
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default)]
+struct RawTxForm {
+    signer: String,
+    tx_type: String,
+    chain_id: String,
+    to: String,
+    value: String,
+    data: String,
+    nonce: String,
+    gas: String,
+    gas_price: String,
+    max_fee_per_gas: String,
+    max_priority_fee_per_gas: String,
+    access_list: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct BroadcastForm {
+    raw: String,
+}
+
+/// Fields of a signed transaction as recovered from its raw encoding.
+#[derive(Serialize, Debug)]
+struct SignedTransaction {
+    raw: String,
+    hash: String,
+    from: String,
+    to: Option<String>,
+    tx_type: u64,
+    chain_id: Option<u64>,
+    nonce: Option<String>,
+    value: String,
+    data_size: usize,
+    access_list_size: usize,
+    fees: AppliedFees,
+}
+
+pub fn setup_handlers(cfg: &mut web::ServiceConfig) {
+    cfg.service(rawtx_handler)
+        .service(fill_handler)
+        .service(sign_handler)
+        .service(broadcast_handler);
+}
+
+#[get("/rawtx")]
+async fn rawtx_handler(app_state: web::Data<AppState>) -> impl Responder {
+    let form = RawTxForm {
+        signer: app_state
+            .eth_client
+            .signer_addresses()
+            .first()
+            .map(|address| format!("{address:#x}"))
+            .unwrap_or_default(),
+        tx_type: "2".to_owned(),
+        chain_id: app_state.eth_client.chain_id().to_string(),
+        value: "0".to_owned(),
+        ..Default::default()
+    };
+    render(&app_state, RAWTX_TEMPLATE, &form)
+}
+
+/// Fills in the nonce, gas limit and fees from the node, keeping the fields already set.
+#[post("/rawtx/fill")]
+async fn fill_handler(
+    form: web::Form<RawTxForm>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let mut form = form.into_inner();
+    if let Err(e) = fill(&app_state, &mut form).await {
+        return helper::ui_alert(&e);
+    }
+    render(&app_state, RAWTX_FORM_TEMPLATE, &form)
+}
+
+/// Signs the transaction with the local wallet of the signer, without contacting the node.
+#[post("/rawtx/sign")]
+async fn sign_handler(
+    form: web::Form<RawTxForm>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let signer = match helper::get_signer_client(&app_state.eth_client, Some(&form.signer)) {
+        Ok(signer) => signer,
+        Err(e) => return helper::ui_alert(&e),
+    };
+    let mut tx = match build_transaction(&form) {
+        Ok(tx) => tx,
+        Err(e) => return helper::ui_alert(&e),
+    };
+    tx.set_from(signer.address());
+    let signature = match signer.signer().sign_transaction_sync(&tx) {
+        Ok(signature) => signature,
+        Err(e) => return helper::ui_alert(&format!("could not sign the transaction: {e}")),
+    };
+
+    let signed = match decode(&tx.rlp_signed(&signature)) {
+        Ok(signed) => signed,
+        Err(e) => return helper::ui_alert(&e),
+    };
+    let mut context = Context::new();
+    context.insert("signed", &signed);
+    match app_state.tmpl.render(RAWTX_RESULT_TEMPLATE, &context) {
+        Ok(rendered) => HttpResponse::Ok().body(rendered),
+        Err(e) => helper::render_error(e),
+    }
+}
+
+/// Sends a signed transaction with `eth_sendRawTransaction` and tracks it.
+#[post("/rawtx/broadcast")]
+async fn broadcast_handler(
+    form: web::Form<BroadcastForm>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let raw = match form.raw.trim().parse::<Bytes>() {
+        Ok(raw) => raw,
+        Err(e) => return helper::ui_alert(&format!("invalid raw transaction: {e}")),
+    };
+    let signed = match decode(&raw) {
+        Ok(signed) => signed,
+        Err(e) => return helper::ui_alert(&e),
+    };
+
+    let client = app_state.eth_client.get_client();
+    let tx_hash = match client.send_raw_transaction(raw).await {
+        Ok(pending_tx) => pending_tx.tx_hash(),
+        Err(e) => return helper::ui_alert(&e.to_string()),
+    };
+    // the nonce was not handed out by the signer's nonce manager
+    let signer = helper::parse_address(&signed.from)
+        .ok()
+        .and_then(|from| app_state.eth_client.get_signer_client(Some(from)));
+    if let Some(signer) = signer {
+        signer.resync().await;
+    }
+
+    app_state
+        .debug_service
+        .send_debug_event(&format!(
+            "<b>[Raw transaction]</b> broadcast {}",
+            helper::tx_link(&tx_hash)
+        ))
+        .await;
+    app_state
+        .tx_tracker
+        .track(
+            tx_hash,
+            TRACKER_LABEL,
+            &signed.fees.to_string(),
+            app_state.eth_client.confirmations(),
+        )
+        .await;
+
+    let mut context = Context::new();
+    context.insert("broadcast_hash", &format!("{tx_hash:#x}"));
+    match app_state.tmpl.render(RAWTX_RESULT_TEMPLATE, &context) {
+        Ok(rendered) => HttpResponse::Ok().body(rendered),
+        Err(e) => helper::render_error(e),
+    }
+}
+
+async fn fill(app_state: &AppState, form: &mut RawTxForm) -> Result<(), String> {
+    let signer = helper::get_signer_client(&app_state.eth_client, Some(&form.signer))?;
+    let client = app_state.eth_client.get_client();
+
+    if form.chain_id.trim().is_empty() {
+        form.chain_id = app_state.eth_client.chain_id().to_string();
+    }
+    if form.nonce.trim().is_empty() {
+        form.nonce = client
+            .get_transaction_count(signer.address(), Some(BlockNumber::Pending.into()))
+            .await
+            .map_err(|e| e.to_string())?
+            .to_string();
+    }
+    match form.tx_type.as_str() {
+        "2" => {
+            if form.max_fee_per_gas.trim().is_empty()
+                || form.max_priority_fee_per_gas.trim().is_empty()
+            {
+                let (max_fee, priority_fee) = app_state
+                    .eth_client
+                    .fee_strategy()
+                    .estimate(&client)
+                    .await?;
+                form.max_fee_per_gas = fees::gwei(max_fee);
+                form.max_priority_fee_per_gas = fees::gwei(priority_fee);
+            }
+        }
+        _ => {
+            if form.gas_price.trim().is_empty() {
+                let gas_price = client.get_gas_price().await.map_err(|e| e.to_string())?;
+                form.gas_price = fees::gwei(gas_price);
+            }
+        }
+    }
+    if form.gas.trim().is_empty() {
+        let mut tx: TypedTransaction = TransactionRequest::new()
+            .from(signer.address())
+            .value(parse_value(&form.value)?)
+            .data(parse_data(&form.data)?)
+            .into();
+        if let Some(to) = parse_to(&form.to)? {
+            tx.set_to(to);
+        }
+        form.gas = client
+            .estimate_gas(&tx, Some(BlockNumber::Pending.into()))
+            .await
+            .map_err(|e| format!("could not estimate gas: {e}"))?
+            .to_string();
+    }
+    Ok(())
+}
+
+/// Builds a transaction of type 0 (legacy), 1 (EIP-2930) or 2 (EIP-1559). All the fields needed
+/// to sign it have to be set, nothing is looked up on the node.
+fn build_transaction(form: &RawTxForm) -> Result<TypedTransaction, String> {
+    let chain_id = required(&form.chain_id, "chain id")?
+        .parse::<u64>()
+        .map_err(|e| format!("invalid chain id: {e}"))?;
+    let nonce = U256::from_dec_str(required(&form.nonce, "nonce")?)
+        .map_err(|e| format!("invalid nonce: {e}"))?;
+    let gas = U256::from_dec_str(required(&form.gas, "gas limit")?)
+        .map_err(|e| format!("invalid gas limit: {e}"))?;
+    let to = parse_to(&form.to)?;
+    let value = parse_value(&form.value)?;
+    let data = parse_data(&form.data)?;
+    let access_list = match form.access_list.trim() {
+        "" => AccessList::default(),
+        access_list => serde_json::from_str::<AccessList>(access_list)
+            .map_err(|e| format!("invalid access list: {e}"))?,
+    };
+
+    let mut legacy = TransactionRequest::new()
+        .chain_id(chain_id)
+        .nonce(nonce)
+        .gas(gas)
+        .value(value)
+        .data(data.clone());
+    legacy.to = to.map(NameOrAddress::Address);
+
+    match form.tx_type.trim() {
+        "0" | "1" => {
+            let gas_price = fees::parse_gwei(Some(&form.gas_price), "gas price")?
+                .ok_or("gas price is required")?;
+            let legacy = legacy.gas_price(gas_price);
+            match form.tx_type.trim() {
+                "0" if !access_list.0.is_empty() => {
+                    Err("legacy transactions have no access list, use type 1".to_owned())
+                }
+                "0" => Ok(legacy.into()),
+                _ => Ok(Eip2930TransactionRequest::new(legacy, access_list).into()),
+            }
+        }
+        "2" => {
+            let max_fee = fees::parse_gwei(Some(&form.max_fee_per_gas), "max fee")?
+                .ok_or("max fee is required")?;
+            let priority_fee =
+                fees::parse_gwei(Some(&form.max_priority_fee_per_gas), "priority fee")?
+                    .ok_or("priority fee is required")?;
+            if max_fee < priority_fee {
+                return Err("max fee is below the priority fee".to_owned());
+            }
+            let mut tx = Eip1559TransactionRequest::new()
+                .chain_id(chain_id)
+                .nonce(nonce)
+                .gas(gas)
+                .value(value)
+                .data(data)
+                .access_list(access_list)
+                .max_fee_per_gas(max_fee)
+                .max_priority_fee_per_gas(priority_fee);
+            tx.to = to.map(NameOrAddress::Address);
+            Ok(tx.into())
+        }
+        other => Err(format!(
+            "invalid transaction type {other}: expected 0, 1 or 2"
+        )),
+    }
+}
+
+/// Decodes a signed raw transaction and recovers its sender.
+fn decode(raw: &Bytes) -> Result<SignedTransaction, String> {
+    let (tx, signature) = TypedTransaction::decode_signed(&rlp::Rlp::new(raw))
+        .map_err(|e| format!("invalid raw transaction: {e}"))?;
+    let from = signature
+        .recover(tx.sighash())
+        .map_err(|e| format!("invalid signature: {e}"))?;
+
+    let (tx_type, access_list_size) = match &tx {
+        TypedTransaction::Legacy(_) => (0, 0),
+        TypedTransaction::Eip2930(tx) => (1, tx.access_list.0.len()),
+        TypedTransaction::Eip1559(tx) => (2, tx.access_list.0.len()),
+    };
+    let fees = AppliedFees {
+        gas_limit: tx.gas().map(|gas| gas.to_string()),
+        max_fee_per_gas: match &tx {
+            TypedTransaction::Eip1559(tx) => tx.max_fee_per_gas.map(fees::gwei),
+            _ => None,
+        },
+        max_priority_fee_per_gas: match &tx {
+            TypedTransaction::Eip1559(tx) => tx.max_priority_fee_per_gas.map(fees::gwei),
+            _ => None,
+        },
+        gas_price: match &tx {
+            TypedTransaction::Eip1559(_) => None,
+            tx => tx.gas_price().map(fees::gwei),
+        },
+        source: TRACKER_LABEL.to_owned(),
+    };
+
+    Ok(SignedTransaction {
+        raw: format!("{raw}"),
+        hash: format!("{:#x}", tx.hash(&signature)),
+        from: format!("{from:#x}"),
+        to: tx.to_addr().map(|to| format!("{to:#x}")),
+        tx_type,
+        chain_id: tx.chain_id().map(|id| id.as_u64()),
+        nonce: tx.nonce().map(|nonce| nonce.to_string()),
+        value: tx.value().copied().unwrap_or_default().to_string(),
+        data_size: tx.data().map_or(0, |data| data.len()),
+        access_list_size,
+        fees,
+    })
+}
+
+fn render(app_state: &AppState, template: &str, form: &RawTxForm) -> HttpResponse {
+    let mut context = Context::new();
+    context.insert("form", form);
+    context.insert("signer_addresses", &app_state.eth_client.signer_addresses());
+    context.insert(
+        "fee_strategy",
+        &app_state.eth_client.fee_strategy().to_string(),
+    );
+    match app_state.tmpl.render(template, &context) {
+        Ok(rendered) => HttpResponse::Ok().body(rendered),
+        Err(e) => helper::render_error(e),
+    }
+}
+
+fn required<'a>(value: &'a str, name: &str) -> Result<&'a str, String> {
+    match value.trim() {
+        "" => Err(format!("{name} is required to sign offline")),
+        value => Ok(value),
+    }
+}
+
+fn parse_to(to: &str) -> Result<Option<Address>, String> {
+    match to.trim() {
+        "" => Ok(None),
+        to => helper::parse_address(to)
+            .map(Some)
+            .map_err(|e| format!("invalid recipient: {e}")),
+    }
+}
+
+fn parse_value(value: &str) -> Result<U256, String> {
+    match value.trim() {
+        "" => Ok(U256::zero()),
+        value => LenientTokenizer::tokenize_uint(value)
+            .map(U256::from)
+            .map_err(|e| format!("invalid value: {e}")),
+    }
+}
+
+fn parse_data(data: &str) -> Result<Bytes, String> {
+    data.trim()
+        .parse::<Bytes>()
+        .map_err(|e| format!("invalid data: {e}"))
+}
@@ -0,0 +1,196 @@
+use crate::{app::model::State as AppState, helper};
+use actix_web::{get, post, web, HttpResponse, Responder};
+use ethers::{
+    types::{
+        transaction::eip712::{Eip712, TypedData},
+        Address, Bytes, RecoveryMessage, Signature, H256,
+    },
+    utils::hash_message,
+};
+use serde::{Deserialize, Serialize};
+use tera::Context;
+
+const SIGNING_TEMPLATE: &str = "signing.html";
+const SIGNING_RESULT_TEMPLATE: &str = "signing_result.html";
+const PERSONAL_MESSAGE: &str = "message";
+const TYPED_DATA: &str = "typed_data";
+
+#[derive(Deserialize, Debug)]
+struct SignForm {
+    signer: String,
+    kind: String,
+    payload: String,
+    hex: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct VerifyForm {
+    kind: String,
+    payload: String,
+    hex: Option<String>,
+    signature: String,
+    address: Option<String>,
+}
+
+/// Hash which is signed for a personal message (EIP-191) or typed data (EIP-712).
+struct Digest {
+    scheme: &'static str,
+    hash: H256,
+    primary_type: Option<String>,
+    domain_separator: Option<H256>,
+}
+
+#[derive(Serialize, Debug)]
+struct SignatureDetails {
+    scheme: String,
+    digest: String,
+    primary_type: Option<String>,
+    domain_separator: Option<String>,
+    signature: String,
+    r: String,
+    s: String,
+    v: u64,
+    high_s: bool,
+    recovered: Option<String>,
+    recover_error: Option<String>,
+    expected: Option<String>,
+    valid: Option<bool>,
+}
+
+pub fn setup_handlers(cfg: &mut web::ServiceConfig) {
+    cfg.service(signing_handler)
+        .service(sign_handler)
+        .service(verify_handler);
+}
+
+#[get("/signing")]
+async fn signing_handler(app_state: web::Data<AppState>) -> impl Responder {
+    let mut context = Context::new();
+    context.insert("signer_addresses", &app_state.eth_client.signer_addresses());
+    context.insert("chain_id", &app_state.eth_client.chain_id());
+    match app_state.tmpl.render(SIGNING_TEMPLATE, &context) {
+        Ok(rendered) => HttpResponse::Ok().body(rendered),
+        Err(e) => helper::render_error(e),
+    }
+}
+
+/// Signs the digest of the message or typed data with the local wallet of the signer.
+#[post("/signing/sign")]
+async fn sign_handler(form: web::Form<SignForm>, app_state: web::Data<AppState>) -> impl Responder {
+    let signer = match helper::get_signer_client(&app_state.eth_client, Some(&form.signer)) {
+        Ok(signer) => signer,
+        Err(e) => return helper::ui_alert(&e),
+    };
+    let digest = match digest(&form.kind, &form.payload, form.hex.is_some()) {
+        Ok(digest) => digest,
+        Err(e) => return helper::ui_alert(&e),
+    };
+    let signature = match signer.signer().sign_hash(digest.hash) {
+        Ok(signature) => signature,
+        Err(e) => return helper::ui_alert(&format!("could not sign: {e}")),
+    };
+    render_details(
+        &app_state,
+        details(&digest, &signature, Some(signer.address())),
+    )
+}
+
+/// Recovers the signer of the message or typed data and compares it to the expected address.
+#[post("/signing/verify")]
+async fn verify_handler(
+    form: web::Form<VerifyForm>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let digest = match digest(&form.kind, &form.payload, form.hex.is_some()) {
+        Ok(digest) => digest,
+        Err(e) => return helper::ui_alert(&e),
+    };
+    let signature = match form.signature.trim().parse::<Signature>() {
+        Ok(signature) => signature,
+        Err(e) => return helper::ui_alert(&format!("invalid signature: {e}")),
+    };
+    let expected = match form.address.as_deref().map(str::trim) {
+        Some(address) if !address.is_empty() => match helper::parse_address(address) {
+            Ok(address) => Some(address),
+            Err(e) => return helper::ui_alert(&e.to_string()),
+        },
+        _ => None,
+    };
+    render_details(&app_state, details(&digest, &signature, expected))
+}
+
+fn digest(kind: &str, payload: &str, hex: bool) -> Result<Digest, String> {
+    match kind {
+        PERSONAL_MESSAGE => {
+            let message = match hex {
+                true => payload
+                    .trim()
+                    .parse::<Bytes>()
+                    .map_err(|e| format!("invalid hex message: {e}"))?
+                    .to_vec(),
+                false => payload.as_bytes().to_vec(),
+            };
+            Ok(Digest {
+                scheme: "EIP-191 personal message",
+                hash: hash_message(message),
+                primary_type: None,
+                domain_separator: None,
+            })
+        }
+        TYPED_DATA => {
+            let typed_data = serde_json::from_str::<TypedData>(payload)
+                .map_err(|e| format!("invalid typed data: {e}"))?;
+            let hash = typed_data
+                .encode_eip712()
+                .map_err(|e| format!("could not encode the typed data: {e}"))?;
+            Ok(Digest {
+                scheme: "EIP-712 typed data",
+                hash: hash.into(),
+                domain_separator: Some(typed_data.domain.separator().into()),
+                primary_type: Some(typed_data.primary_type),
+            })
+        }
+        other => Err(format!("unknown message kind {other}")),
+    }
+}
+
+fn details(digest: &Digest, signature: &Signature, expected: Option<Address>) -> SignatureDetails {
+    let recovered = signature.recover(RecoveryMessage::Hash(digest.hash));
+    let mut r = [0u8; 32];
+    let mut s = [0u8; 32];
+    signature.r.to_big_endian(&mut r);
+    signature.s.to_big_endian(&mut s);
+    // signatures with s in the upper half of the curve order are malleable and rejected by
+    // `ecrecover` based checks such as OpenZeppelin's `ECDSA.recover`
+    let high_s = k256::ecdsa::Signature::from_scalars(r, s)
+        .map(|signature| signature.normalize_s().is_some())
+        .unwrap_or_default();
+
+    SignatureDetails {
+        scheme: digest.scheme.to_owned(),
+        digest: format!("{:#x}", digest.hash),
+        primary_type: digest.primary_type.clone(),
+        domain_separator: digest.domain_separator.map(|hash| format!("{hash:#x}")),
+        signature: format!("0x{signature}"),
+        r: format!("{:#x}", H256::from(r)),
+        s: format!("{:#x}", H256::from(s)),
+        v: signature.v,
+        high_s,
+        recovered: recovered
+            .as_ref()
+            .ok()
+            .map(|address| format!("{address:#x}")),
+        recover_error: recovered.as_ref().err().map(|e| e.to_string()),
+        expected: expected.map(|address| format!("{address:#x}")),
+        valid: expected.map(|expected| recovered.is_ok_and(|recovered| recovered == expected)),
+    }
+}
+
+fn render_details(app_state: &AppState, details: SignatureDetails) -> HttpResponse {
+    let mut context = Context::new();
+    context.insert("details", &details);
+    match app_state.tmpl.render(SIGNING_RESULT_TEMPLATE, &context) {
+        Ok(rendered) => HttpResponse::Ok().body(rendered),
+        Err(e) => helper::render_error(e),
+    }
+}
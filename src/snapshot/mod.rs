@@ -0,0 +1,169 @@
+use crate::{
+    app::model::{Snapshot, State as AppState},
+    helper,
+};
+use actix_web::{get, post, web, HttpResponse, Responder};
+use chrono::Local;
+use ethers::types::U256;
+use ethers_providers::Middleware;
+use serde::Deserialize;
+use tera::Context;
+
+const SNAPSHOTS_TEMPLATE: &str = "snapshots.html";
+const SNAPSHOT_LIST_TEMPLATE: &str = "snapshot_list.html";
+const DATE_FORMAT: &str = "%d/%m/%Y %T";
+
+#[derive(Deserialize, Debug)]
+struct NewSnapshotForm {
+    name: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct RevertForm {
+    id: U256,
+}
+
+pub fn setup_handlers(cfg: &mut web::ServiceConfig) {
+    cfg.service(snapshots_handler)
+        .service(new_snapshot_handler)
+        .service(revert_handler);
+}
+
+#[get("/snapshots")]
+async fn snapshots_handler(app_state: web::Data<AppState>) -> impl Responder {
+    let context = snapshot_context(&app_state).await;
+    match app_state.tmpl.render(SNAPSHOTS_TEMPLATE, &context) {
+        Ok(rendered) => HttpResponse::Ok().body(rendered),
+        Err(e) => helper::render_error(e),
+    }
+}
+
+#[post("/snapshots")]
+async fn new_snapshot_handler(
+    form: web::Form<NewSnapshotForm>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let eth = app_state.eth_client.get_client();
+    let block_number = match eth.get_block_number().await {
+        Ok(block_number) => block_number.as_u64(),
+        Err(e) => return helper::ui_alert(&e.to_string()),
+    };
+    let id = match app_state.eth_client.snapshot().await {
+        Ok(id) => id,
+        Err(e) => return helper::ui_alert(&e.to_string()),
+    };
+
+    let mut snapshots = app_state.snapshots.lock().await;
+    let name = match form.name.trim() {
+        "" => format!("snapshot {}", snapshots.len() + 1),
+        name => name.to_owned(),
+    };
+    app_state
+        .debug_service
+        .send_debug_event(&format!(
+            "<b>[Snapshots]</b> took snapshot {name} at block {block_number}"
+        ))
+        .await;
+    snapshots.push(Snapshot {
+        id,
+        name,
+        block_number,
+        created_at: Local::now().format(DATE_FORMAT).to_string(),
+    });
+    drop(snapshots);
+
+    render_snapshot_list(&app_state, None).await
+}
+
+#[post("/snapshots/revert")]
+async fn revert_handler(
+    form: web::Form<RevertForm>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let mut snapshots = app_state.snapshots.lock().await;
+    let name = match snapshots.iter().find(|s| s.id == form.id) {
+        Some(snapshot) => snapshot.name.clone(),
+        None => return helper::ui_alert(&format!("snapshot {} not found", form.id)),
+    };
+
+    let reverted = match app_state.eth_client.revert_to_snapshot(form.id).await {
+        Ok(reverted) => reverted,
+        Err(e) => return helper::ui_alert(&e.to_string()),
+    };
+    if !reverted {
+        return helper::ui_alert(&format!("node could not revert to snapshot {name}"));
+    }
+    // the node drops the reverted snapshot and all snapshots taken after it
+    snapshots.retain(|s| s.id < form.id);
+    drop(snapshots);
+    app_state
+        .debug_service
+        .send_debug_event(&format!("<b>[Snapshots]</b> reverted to snapshot {name}"))
+        .await;
+
+    app_state.tx_tracker.recheck().await;
+    if let Err(e) = invalidate_contracts(&app_state).await {
+        return helper::ui_alert(&e);
+    }
+
+    render_snapshot_list(&app_state, Some("loadBlockDetails, loadAccountBalances")).await
+}
+
+/// Drops the contract instances and deployments which have no code anymore (e.g. after a revert).
+pub async fn invalidate_contracts(app_state: &AppState) -> Result<(), String> {
+    let eth = app_state.eth_client.get_client();
+    let mut lock = app_state.contracts.lock().await;
+    let mut removed = vec![];
+    for (name, instances) in lock.iter() {
+        for (label, contract) in instances.iter() {
+            match eth.get_code(contract.address(), None).await {
+                Ok(code) if code.is_empty() => removed.push((name.clone(), label.clone())),
+                Ok(_) => {}
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+    }
+
+    for (name, label) in removed.iter() {
+        if let Some(instances) = lock.get_mut(name) {
+            instances.remove(label);
+            if instances.is_empty() {
+                lock.remove(name);
+            }
+        }
+        app_state
+            .debug_service
+            .send_debug_event(&format!(
+                "<b>[{name}]</b> instance {label} has no code anymore and was removed"
+            ))
+            .await;
+    }
+    drop(lock);
+
+    app_state
+        .deployments
+        .prune(&app_state.eth_client)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+async fn snapshot_context(app_state: &AppState) -> Context {
+    let mut context = Context::new();
+    context.insert("snapshots", &*app_state.snapshots.lock().await);
+    context
+}
+
+async fn render_snapshot_list(app_state: &AppState, trigger: Option<&str>) -> HttpResponse {
+    let context = snapshot_context(app_state).await;
+    match app_state.tmpl.render(SNAPSHOT_LIST_TEMPLATE, &context) {
+        Ok(rendered) => {
+            let mut response = HttpResponse::Ok();
+            if let Some(trigger) = trigger {
+                response.append_header(("HX-Trigger", trigger));
+            }
+            response.body(rendered)
+        }
+        Err(e) => helper::render_error(e),
+    }
+}
@@ -0,0 +1,45 @@
+use crate::{
+    app::{model::State as AppState, txtracker::TRANSACTIONS_TEMPLATE},
+    helper,
+};
+use actix_web::{get, post, web, HttpResponse, Responder};
+use ethers::types::H256;
+use tera::Context;
+
+pub fn setup_handlers(cfg: &mut web::ServiceConfig) {
+    cfg.service(transactions_handler)
+        .service(speed_up_handler)
+        .service(cancel_handler);
+}
+
+#[get("/transactions")]
+async fn transactions_handler(app_state: web::Data<AppState>) -> impl Responder {
+    render_transactions(&app_state).await
+}
+
+#[post("/transactions/{hash}/speed-up")]
+async fn speed_up_handler(path: web::Path<H256>, app_state: web::Data<AppState>) -> impl Responder {
+    match app_state.tx_tracker.speed_up(path.into_inner()).await {
+        Ok(_) => render_transactions(&app_state).await,
+        Err(e) => helper::ui_alert(&e),
+    }
+}
+
+#[post("/transactions/{hash}/cancel")]
+async fn cancel_handler(path: web::Path<H256>, app_state: web::Data<AppState>) -> impl Responder {
+    match app_state.tx_tracker.cancel(path.into_inner()).await {
+        Ok(_) => render_transactions(&app_state).await,
+        Err(e) => helper::ui_alert(&e),
+    }
+}
+
+async fn render_transactions(app_state: &AppState) -> HttpResponse {
+    let mut context = Context::new();
+    context.insert("transactions", &app_state.tx_tracker.transactions().await);
+    context.insert("oob", &false);
+    context.insert("reload", &false);
+    match app_state.tmpl.render(TRANSACTIONS_TEMPLATE, &context) {
+        Ok(rendered) => HttpResponse::Ok().body(rendered),
+        Err(e) => helper::render_error(e),
+    }
+}